@@ -0,0 +1,616 @@
+//! Procedural macros for keymacro.
+
+use {
+    proc_macro::{
+        Delimiter,
+        Group,
+        Ident,
+        Literal,
+        Punct,
+        Spacing,
+        Span,
+        TokenStream,
+        TokenTree,
+    },
+    std::{
+        env,
+        str::FromStr,
+        time::{
+            SystemTime,
+            UNIX_EPOCH,
+        },
+    },
+};
+
+/// Pastes identifiers in `[< ... >]`.
+///
+/// See `keymacro::paste` for the details.
+#[proc_macro]
+pub fn paste(input: TokenStream) -> TokenStream {
+    match paste_stream(input) {
+        Result::Ok(output) => output,
+        Result::Err((message, span)) => compile_error(message, span),
+    }
+}
+
+fn paste_stream(input: TokenStream) -> Result<TokenStream, (String, Span)> {
+    let mut output = Vec::new();
+
+    for token in input {
+        match token {
+            TokenTree::Group(group) => match pasted_segments(&group) {
+                Option::Some(segments) => output.push(paste_segments(segments, group.span())?),
+                Option::None => {
+                    let mut pasted = Group::new(group.delimiter(), paste_stream(group.stream())?);
+                    pasted.set_span(group.span());
+                    output.push(TokenTree::Group(pasted));
+                },
+            },
+            token => output.push(token),
+        }
+    }
+
+    Result::Ok(output.into_iter().collect())
+}
+
+fn pasted_segments(group: &Group) -> Option<Vec<TokenTree>> {
+    if group.delimiter() != Delimiter::Bracket {
+        return Option::None;
+    }
+
+    let tokens = group.stream().into_iter().collect::<Vec<_>>();
+
+    match tokens.as_slice() {
+        [
+            TokenTree::Punct(open),
+            segments @ ..,
+            TokenTree::Punct(close),
+        ] if open.as_char() == '<' && close.as_char() == '>' => Option::Some(segments.to_vec()),
+        _ => Option::None,
+    }
+}
+
+fn paste_segments(segments: Vec<TokenTree>, span: Span) -> Result<TokenTree, (String, Span)> {
+    let mut pasted = String::new();
+    let mut first_span = Option::None;
+    let mut segments = segments.into_iter().peekable();
+
+    while let Option::Some(segment) = segments.next() {
+        first_span.get_or_insert(segment.span());
+
+        let mut text = match segment {
+            TokenTree::Ident(ident) => ident.to_string().trim_start_matches("r#").to_owned(),
+            TokenTree::Literal(literal) => literal_text(&literal),
+            TokenTree::Group(group) if group.delimiter() == Delimiter::None => {
+                let mut text = String::new();
+
+                for token in group.stream() {
+                    match token {
+                        TokenTree::Ident(ident) => text.push_str(&ident.to_string()),
+                        TokenTree::Literal(literal) => text.push_str(&literal_text(&literal)),
+                        token => {
+                            return Result::Err((
+                                "expected an identifier".to_owned(),
+                                token.span(),
+                            ));
+                        },
+                    }
+                }
+
+                text
+            },
+            token => return Result::Err(("expected an identifier".to_owned(), token.span())),
+        };
+
+        if let Option::Some(TokenTree::Punct(punct)) = segments.peek() {
+            if punct.as_char() == ':' {
+                let punct = punct.span();
+                segments.next();
+
+                text = match segments.next() {
+                    Option::Some(TokenTree::Ident(modifier)) if modifier.to_string() == "lower" => {
+                        text.to_lowercase()
+                    },
+                    Option::Some(TokenTree::Ident(modifier)) if modifier.to_string() == "upper" => {
+                        text.to_uppercase()
+                    },
+                    _ => return Result::Err(("expected `lower` or `upper`".to_owned(), punct)),
+                };
+            }
+        }
+
+        pasted.push_str(&text);
+    }
+
+    let span = first_span.unwrap_or(span);
+    let error = || (format!("`{pasted}` is not a valid identifier"), span);
+    let mut chars = pasted.chars();
+
+    let valid = chars
+        .next()
+        .is_some_and(|first| first == '_' || first.is_alphabetic())
+        && chars.all(|char| char == '_' || char.is_alphanumeric());
+
+    if !valid {
+        return Result::Err(error());
+    }
+
+    // The lexer decides, so that an identifier `Ident::new` would reject is an
+    // error instead of a panic.
+    let mut tokens = TokenStream::from_str(&pasted)
+        .map_err(|_| error())?
+        .into_iter();
+
+    match (tokens.next(), tokens.next()) {
+        (Option::Some(TokenTree::Ident(ident)), Option::None) => {
+            Result::Ok(TokenTree::Ident(Ident::new(&ident.to_string(), span)))
+        },
+        _ => Result::Err(error()),
+    }
+}
+
+fn literal_text(literal: &Literal) -> String {
+    let text = literal.to_string();
+
+    match text
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+    {
+        Option::Some(text) => text.to_owned(),
+        Option::None => text,
+    }
+}
+
+/// Rewrites `#[defer] { ... }` statements in a function body into guards.
+///
+/// See `keymacro::guarded` for the details.
+#[proc_macro_attribute]
+pub fn guarded(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    let path = match crate_path(attribute) {
+        Result::Ok(path) => path,
+        Result::Err((message, span)) => return compile_error(message, span),
+    };
+
+    let mut tokens = item.into_iter().collect::<Vec<_>>();
+
+    match tokens.last_mut() {
+        Option::Some(TokenTree::Group(body)) if body.delimiter() == Delimiter::Brace => {
+            let mut guarded = Group::new(Delimiter::Brace, guarded_body(body.stream(), &path));
+            guarded.set_span(body.span());
+            *body = guarded;
+            tokens.into_iter().collect()
+        },
+        _ => compile_error("expected a function".to_owned(), Span::call_site()),
+    }
+}
+
+// Returns the path to keymacro, which is `::keymacro` unless `crate = path` is
+// given.
+fn crate_path(attribute: TokenStream) -> Result<TokenStream, (String, Span)> {
+    let tokens = attribute.into_iter().collect::<Vec<_>>();
+
+    match tokens.as_slice() {
+        [] => Result::Ok(TokenStream::from_str("::keymacro").unwrap()),
+        [TokenTree::Ident(name), TokenTree::Punct(equal), path @ ..]
+            if name.to_string() == "crate" && equal.as_char() == '=' && !path.is_empty() =>
+        {
+            Result::Ok(path.iter().cloned().collect())
+        },
+        [token, ..] => Result::Err(("expected `crate = path`".to_owned(), token.span())),
+    }
+}
+
+fn guarded_body(body: TokenStream, path: &TokenStream) -> TokenStream {
+    let mut output = Vec::new();
+    let mut tokens = body.into_iter().peekable();
+    let mut statement_start = true;
+
+    while let Option::Some(token) = tokens.next() {
+        if statement_start && matches!(&token, TokenTree::Punct(punct) if punct.as_char() == '#') {
+            if let Option::Some(moved) = match tokens.peek() {
+                Option::Some(TokenTree::Group(attribute)) => defer_attribute(attribute),
+                _ => Option::None,
+            } {
+                let span = tokens.next().unwrap().span();
+
+                match tokens.next() {
+                    Option::Some(TokenTree::Group(block))
+                        if block.delimiter() == Delimiter::Brace =>
+                    {
+                        output.extend(deferred(moved, block, span, path));
+                        continue;
+                    },
+                    _ => {
+                        return compile_error("expected a block after `#[defer]`".to_owned(), span);
+                    },
+                }
+            }
+        }
+
+        statement_start = match &token {
+            TokenTree::Punct(punct) => punct.as_char() == ';',
+            TokenTree::Group(group) => group.delimiter() == Delimiter::Brace,
+            _ => false,
+        };
+
+        output.push(token);
+    }
+
+    output.into_iter().collect()
+}
+
+fn deferred(moved: bool, block: Group, span: Span, path: &TokenStream) -> TokenStream {
+    let mut deferred = Vec::new();
+
+    if moved {
+        deferred.push(TokenTree::Ident(Ident::new("move", span)));
+    }
+
+    deferred.push(TokenTree::Group(block));
+
+    let mut group = Group::new(Delimiter::Brace, deferred.into_iter().collect());
+    group.set_span(span);
+
+    let mut output = path.clone();
+    output.extend(TokenStream::from_str("::defer!").unwrap());
+    output.extend([TokenTree::Group(group)]);
+    output
+}
+
+fn defer_attribute(attribute: &Group) -> Option<bool> {
+    if attribute.delimiter() != Delimiter::Bracket {
+        return Option::None;
+    }
+
+    let tokens = attribute.stream().into_iter().collect::<Vec<_>>();
+
+    match tokens.as_slice() {
+        [TokenTree::Ident(name)] if name.to_string() == "defer" => Option::Some(false),
+        [TokenTree::Ident(name), TokenTree::Group(arguments)]
+            if name.to_string() == "defer"
+                && arguments.delimiter() == Delimiter::Parenthesis
+                && arguments.stream().to_string() == "move" =>
+        {
+            Option::Some(true)
+        },
+        _ => Option::None,
+    }
+}
+
+fn respan(token: TokenTree, span: Span) -> TokenTree {
+    match token {
+        TokenTree::Group(group) => {
+            let mut respanned = Group::new(
+                group.delimiter(),
+                group
+                    .stream()
+                    .into_iter()
+                    .map(|token| respan(token, span))
+                    .collect(),
+            );
+
+            respanned.set_span(span);
+            TokenTree::Group(respanned)
+        },
+        mut token => {
+            token.set_span(span);
+            token
+        },
+    }
+}
+
+/// Fails to compile after a date.
+///
+/// See `keymacro::todo_by` for the details.
+#[proc_macro]
+pub fn todo_by(input: TokenStream) -> TokenStream {
+    match todo_by_item(input) {
+        Result::Ok(output) => output,
+        Result::Err((message, span)) => compile_error(message, span),
+    }
+}
+
+fn todo_by_item(input: TokenStream) -> Result<TokenStream, (String, Span)> {
+    let mut tokens = input.into_iter().collect::<Vec<_>>();
+
+    if matches!(tokens.last(), Option::Some(TokenTree::Punct(comma)) if comma.as_char() == ',') {
+        tokens.pop();
+    }
+
+    let (date, message) = match tokens.as_slice() {
+        [TokenTree::Literal(date)] => (date, Option::None),
+        [
+            TokenTree::Literal(date),
+            TokenTree::Punct(comma),
+            TokenTree::Literal(message),
+        ] if comma.as_char() == ',' => (date, Option::Some(message)),
+        [token, ..] => {
+            return Result::Err(("expected a date and a message".to_owned(), token.span()));
+        },
+        [] => {
+            return Result::Err((
+                "expected a date and a message".to_owned(),
+                Span::call_site(),
+            ));
+        },
+    };
+
+    let span = date.span();
+
+    let deadline = string_literal(date)
+        .as_deref()
+        .and_then(parse_date)
+        .ok_or_else(|| ("expected a date in the form `YYYY-MM-DD`".to_owned(), span))?;
+
+    let message = match message {
+        Option::Some(message) => match string_literal(message) {
+            Option::Some(message) => format!(": {message}"),
+            Option::None => {
+                return Result::Err(("expected a string literal".to_owned(), message.span()));
+            },
+        },
+        Option::None => String::new(),
+    };
+
+    if today().map_err(|message| (message, span))? > deadline {
+        let (year, month, day) = deadline;
+        return Result::Err((
+            format!("TODO expired on {year:04}-{month:02}-{day:02}{message}"),
+            span,
+        ));
+    }
+
+    Result::Ok(TokenStream::from_str("const _: () = ();").unwrap())
+}
+
+fn string_literal(literal: &Literal) -> Option<String> {
+    let text = literal.to_string();
+
+    text.strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .filter(|text| !text.contains('\\'))
+        .map(str::to_owned)
+}
+
+fn parse_date(text: &str) -> Option<(i64, u32, u32)> {
+    let mut parts = text.split('-');
+
+    let (year, month, day) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Option::Some(year), Option::Some(month), Option::Some(day), Option::None)
+            if year.len() == 4 && month.len() == 2 && day.len() == 2 =>
+        {
+            (year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+        },
+        _ => return Option::None,
+    };
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return Option::None,
+    };
+
+    (1..=days).contains(&day).then_some((year, month, day))
+}
+
+fn today() -> Result<(i64, u32, u32), String> {
+    let seconds = match env::var("SOURCE_DATE_EPOCH") {
+        Result::Ok(epoch) => epoch
+            .parse::<i64>()
+            .map_err(|_| "`SOURCE_DATE_EPOCH` is not a number".to_owned())?,
+        Result::Err(_) => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| "the system clock is before 1970".to_owned())?
+            .as_secs() as i64,
+    };
+
+    // Converts days since 1970-01-01 to a civil date.
+    let days = seconds.div_euclid(86400) + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+    let month = if month < 10 {
+        month + 3
+    } else {
+        month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    Result::Ok((year, month, day))
+}
+
+/// Unrolls a `for` loop over a literal range.
+///
+/// See `keymacro::unroll` for the details.
+#[proc_macro]
+pub fn unroll(input: TokenStream) -> TokenStream {
+    match unroll_loop(input) {
+        Result::Ok(output) => output,
+        Result::Err((message, span)) => compile_error(message, span),
+    }
+}
+
+fn unroll_loop(input: TokenStream) -> Result<TokenStream, (String, Span)> {
+    let tokens = input.into_iter().collect::<Vec<_>>();
+
+    let (index, start, inclusive, end, body) = match tokens.as_slice() {
+        [
+            TokenTree::Ident(keyword),
+            TokenTree::Ident(index),
+            TokenTree::Ident(r#in),
+            TokenTree::Literal(start),
+            TokenTree::Punct(first),
+            TokenTree::Punct(second),
+            rest @ ..,
+        ] if keyword.to_string() == "for"
+            && r#in.to_string() == "in"
+            && first.as_char() == '.'
+            && second.as_char() == '.' =>
+        {
+            match rest {
+                [TokenTree::Literal(end), TokenTree::Group(body)]
+                    if body.delimiter() == Delimiter::Brace =>
+                {
+                    (index, start, false, end, body)
+                },
+                [
+                    TokenTree::Punct(equal),
+                    TokenTree::Literal(end),
+                    TokenTree::Group(body),
+                ] if equal.as_char() == '=' && body.delimiter() == Delimiter::Brace => {
+                    (index, start, true, end, body)
+                },
+                _ => {
+                    return Result::Err((
+                        "expected `for i in START..END { ... }`".to_owned(),
+                        rest.first().map_or_else(Span::call_site, TokenTree::span),
+                    ));
+                },
+            }
+        },
+        _ => {
+            return Result::Err((
+                "expected `for i in START..END { ... }`".to_owned(),
+                Span::call_site(),
+            ));
+        },
+    };
+
+    let (start, suffix) = integer_literal(start)?;
+    let (end, end_suffix) = integer_literal(end)?;
+
+    let suffix = match (suffix, end_suffix) {
+        (suffix, end_suffix) if suffix == end_suffix || end_suffix.is_empty() => suffix,
+        (suffix, end_suffix) if suffix.is_empty() => end_suffix,
+        _ => {
+            return Result::Err((
+                "the bounds have different types".to_owned(),
+                Span::call_site(),
+            ));
+        },
+    };
+
+    let indices = if inclusive {
+        start..end.saturating_add(1)
+    } else {
+        start..end
+    };
+
+    let mut output = TokenStream::new();
+
+    for value in indices {
+        let literal = TokenStream::from_str(&format!("{value}{suffix}"))
+            .map_err(|error| (error.to_string(), body.span()))?;
+
+        let body = match index.to_string().as_str() {
+            "_" => body.clone(),
+            index => substitute(body, index, &literal),
+        };
+
+        output.extend([
+            TokenTree::Group(body),
+            TokenTree::Punct(Punct::new(';', Spacing::Alone)),
+        ]);
+    }
+
+    Result::Ok(TokenTree::Group(Group::new(Delimiter::Brace, output)).into())
+}
+
+fn integer_literal(literal: &Literal) -> Result<(u128, String), (String, Span)> {
+    let text = literal.to_string().replace('_', "");
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (value, suffix) = text.split_at(digits);
+
+    const SUFFIXES: [&str; 13] = [
+        "", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+    ];
+
+    match value.parse() {
+        Result::Ok(value) if SUFFIXES.contains(&suffix) => Result::Ok((value, suffix.to_owned())),
+        _ => Result::Err((
+            "expected a non-negative integer literal".to_owned(),
+            literal.span(),
+        )),
+    }
+}
+
+fn substitute(group: &Group, index: &str, literal: &TokenStream) -> Group {
+    let tokens = group.stream().into_iter().collect::<Vec<_>>();
+    let mut stream = TokenStream::new();
+
+    for (position, token) in tokens.iter().enumerate() {
+        match token {
+            TokenTree::Ident(ident)
+                if ident.to_string() == index && is_value(&tokens, position) =>
+            {
+                stream.extend(
+                    literal
+                        .clone()
+                        .into_iter()
+                        .map(|token| respan(token, ident.span())),
+                );
+            },
+            TokenTree::Group(inner) => {
+                stream.extend([TokenTree::Group(substitute(inner, index, literal))])
+            },
+            token => stream.extend([token.clone()]),
+        }
+    }
+
+    let mut substituted = Group::new(group.delimiter(), stream);
+    substituted.set_span(group.span());
+    substituted
+}
+
+// Returns whether the ident at the position is a use of a value, not a field, a
+// method, a macro, a path segment, a field name in a struct literal or a
+// lifetime.
+fn is_value(tokens: &[TokenTree], position: usize) -> bool {
+    let punct = |offset: Option<usize>| match offset.and_then(|offset| tokens.get(offset)) {
+        Option::Some(TokenTree::Punct(punct)) => Option::Some((punct.as_char(), punct.spacing())),
+        _ => Option::None,
+    };
+
+    let previous = punct(position.checked_sub(1));
+    let before_previous = punct(position.checked_sub(2));
+    let next = punct(position.checked_add(1));
+
+    let field = previous == Option::Some(('.', Spacing::Alone))
+        && before_previous != Option::Some(('.', Spacing::Joint));
+    let lifetime = previous == Option::Some(('\'', Spacing::Joint));
+    let path = previous == Option::Some((':', Spacing::Alone))
+        && before_previous == Option::Some((':', Spacing::Joint));
+    let r#macro = next == Option::Some(('!', Spacing::Alone));
+    let name = matches!(next, Option::Some((':', _)));
+
+    !(field || lifetime || path || r#macro || name)
+}
+
+fn compile_error(message: String, span: Span) -> TokenStream {
+    let mut literal = Literal::string(&message);
+    literal.set_span(span);
+    let mut group = Group::new(Delimiter::Brace, TokenTree::Literal(literal).into());
+    group.set_span(span);
+
+    [
+        TokenTree::Punct(Punct::new(':', Spacing::Joint)),
+        TokenTree::Punct(Punct::new(':', Spacing::Alone)),
+        TokenTree::Ident(Ident::new("core", span)),
+        TokenTree::Punct(Punct::new(':', Spacing::Joint)),
+        TokenTree::Punct(Punct::new(':', Spacing::Alone)),
+        TokenTree::Ident(Ident::new("compile_error", span)),
+        TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+        TokenTree::Group(group),
+    ]
+    .into_iter()
+    .collect()
+}
@@ -2,6 +2,37 @@
 
 #![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+use alloc::{
+    boxed::Box,
+    vec::Vec,
+};
+use core::{
+    cell::UnsafeCell,
+    fmt::{
+        self,
+        Debug,
+        Formatter,
+    },
+    mem::ManuallyDrop,
+    ops::{
+        Deref,
+        DerefMut,
+    },
+    sync::atomic::{
+        AtomicBool,
+        AtomicPtr,
+        AtomicU8,
+        AtomicUsize,
+        Ordering,
+    },
+};
+
 /// Keeps the value alive.
 ///
 /// # Examples
@@ -23,16 +54,127 @@
 /// keep!(Rc::clone(&rc));
 /// assert_eq!(Rc::strong_count(&rc), 2);
 /// ```
+///
+/// Many values can be kept at once. They are dropped in the reverse order, like
+/// `let` bindings. The hidden bindings never clash with the caller's names.
+///
+/// ```
+/// use {
+///     keymacro::{
+///         Defer,
+///         keep,
+///     },
+///     std::cell::RefCell,
+/// };
+///
+/// let order = RefCell::new(Vec::new());
+///
+/// {
+///     keep!(
+///         Defer::new(|| RefCell::borrow_mut(&order).push(1)),
+///         Defer::new(|| RefCell::borrow_mut(&order).push(2)),
+///     );
+/// }
+///
+/// assert_eq!(*RefCell::borrow(&order), [2, 1]);
+/// ```
+///
+/// `let` binds a shared reference to the kept value.
+///
+/// ```
+/// use {
+///     keymacro::keep,
+///     std::rc::Rc,
+/// };
+///
+/// let rc = Rc::new(());
+/// keep!(let kept = Rc::clone(&rc));
+/// assert!(Rc::ptr_eq(kept, &rc));
+/// assert_eq!(Rc::strong_count(&rc), 2);
+/// ```
 #[macro_export]
 macro_rules! keep {
-    ($value:expr) => {
+    (let $name:pat = $value:expr) => {
         #[allow(non_snake_case)]
         let __keymacro_keep__keeped_value = $value;
+        let $name = &__keymacro_keep__keeped_value;
+    };
+    ($($value:expr),+ $(,)?) => {
+        $(
+            #[allow(non_snake_case)]
+            let __keymacro_keep__keeped_value = $value;
+        )+
+    };
+}
+
+/// Keeps the value alive and pins it in place.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::keep_pinned,
+///     std::{
+///         future::Future,
+///         task::{
+///             Context,
+///             Poll,
+///             Waker,
+///         },
+///     },
+/// };
+///
+/// keep_pinned!(let mut future = async { 1 });
+/// let mut context = Context::from_waker(Waker::noop());
+/// assert_eq!(Future::poll(future.as_mut(), &mut context), Poll::Ready(1));
+/// ```
+#[macro_export]
+macro_rules! keep_pinned {
+    (let $name:pat = $value:expr) => {
+        let $name = ::core::pin::pin!($value);
+    };
+}
+
+/// Leaks the value to make it live forever.
+///
+/// This requires the `alloc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::leak;
+///
+/// let leaked: &'static mut Vec<i32> = leak!(Vec::new());
+/// leaked.push(1);
+/// assert_eq!(leaked, &[1]);
+/// ```
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! leak {
+    ($value:expr $(,)?) => {
+        $crate::__private::Box::leak($crate::__private::Box::new($value))
     };
 }
 
+/// A closure deferred by [`Defer`].
+///
+/// It is implemented for every closure without arguments, and the returned value
+/// is dropped.
+pub trait DeferFn {
+    /// Calls the closure and drops the returned value.
+    fn call(self);
+}
+
+impl<T, F: FnOnce() -> T> DeferFn for F {
+    fn call(self) {
+        self();
+    }
+}
+
 /// A RAII for deferring.
 ///
+/// The deferred closure may return a value, which is dropped.
+///
 /// # Examples
 ///
 /// ```
@@ -51,27 +193,203 @@ macro_rules! keep {
 /// assert!(Cell::get(&changed));
 /// ```
 #[must_use]
-pub struct Defer<F: FnOnce()> {
+pub struct Defer<F: DeferFn> {
     deferred: Option<F>,
 }
 
-impl<F: FnOnce()> Defer<F> {
+impl<F: DeferFn> Defer<F> {
     /// Creates a new instance.
     pub const fn new(deferred: F) -> Self {
         Self {
             deferred: Option::Some(deferred),
         }
     }
+
+    /// Disarms the guard without running the deferred closure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use {
+    ///     keymacro::Defer,
+    ///     std::cell::Cell,
+    /// };
+    ///
+    /// let changed = Cell::new(false);
+    ///
+    /// {
+    ///     let defer = Defer::new(|| Cell::set(&changed, true));
+    ///     Defer::cancel(defer);
+    /// }
+    ///
+    /// assert!(!Cell::get(&changed));
+    /// ```
+    pub fn cancel(mut self) {
+        self.deferred = Option::None;
+    }
+
+    /// Disarms the guard and returns the deferred closure.
+    ///
+    /// It returns `None` if the guard is already disarmed by
+    /// [`run_now`](Self::run_now).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use {
+    ///     keymacro::Defer,
+    ///     std::cell::Cell,
+    /// };
+    ///
+    /// let changed = Cell::new(false);
+    /// let defer = Defer::new(|| Cell::set(&changed, true));
+    /// let deferred = Defer::into_inner(defer).unwrap();
+    /// assert!(!Cell::get(&changed));
+    /// deferred();
+    /// assert!(Cell::get(&changed));
+    ///
+    /// let mut defer = Defer::new(|| {});
+    /// Defer::run_now(&mut defer);
+    /// assert!(Defer::into_inner(defer).is_none());
+    /// ```
+    pub fn into_inner(mut self) -> Option<F> {
+        self.deferred.take()
+    }
+
+    /// Runs the deferred closure now and disarms the guard.
+    ///
+    /// Calling this again does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use {
+    ///     keymacro::Defer,
+    ///     std::cell::Cell,
+    /// };
+    ///
+    /// let count = Cell::new(0);
+    ///
+    /// {
+    ///     let mut defer = Defer::new(|| Cell::set(&count, Cell::get(&count) + 1));
+    ///     Defer::run_now(&mut defer);
+    ///     assert_eq!(Cell::get(&count), 1);
+    ///     Defer::run_now(&mut defer);
+    /// }
+    ///
+    /// assert_eq!(Cell::get(&count), 1);
+    /// ```
+    pub fn run_now(&mut self) {
+        if let Option::Some(deferred) = self.deferred.take() {
+            DeferFn::call(deferred);
+        }
+    }
+
+    /// Returns whether the deferred closure will run.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use keymacro::Defer;
+    ///
+    /// let mut defer = Defer::new(|| {});
+    /// assert!(Defer::is_armed(&defer));
+    /// assert_eq!(format!("{defer:?}"), "Defer { armed: true }");
+    /// Defer::run_now(&mut defer);
+    /// assert!(!Defer::is_armed(&defer));
+    /// ```
+    pub fn is_armed(&self) -> bool {
+        self.deferred.is_some()
+    }
+
+    /// Combines the guard with another closure running after the deferred one.
+    ///
+    /// The closure also runs if the deferred one panics or the guard is
+    /// disarmed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use {
+    ///     keymacro::Defer,
+    ///     std::cell::RefCell,
+    /// };
+    ///
+    /// let log = RefCell::new(Vec::new());
+    ///
+    /// {
+    ///     let defer = Defer::new(|| RefCell::borrow_mut(&log).push("first"));
+    ///     let defer = Defer::and_then(defer, || RefCell::borrow_mut(&log).push("second"));
+    ///     let _defer = Defer::and_then(defer, || RefCell::borrow_mut(&log).push("third"));
+    /// }
+    ///
+    /// assert_eq!(*RefCell::borrow(&log), ["first", "second", "third"]);
+    /// ```
+    pub fn and_then<G: DeferFn>(mut self, next: G) -> Defer<impl FnOnce()> {
+        let deferred = self.deferred.take();
+
+        Defer::new(move || {
+            let _next = Defer::new(next);
+
+            if let Option::Some(deferred) = deferred {
+                DeferFn::call(deferred);
+            }
+        })
+    }
 }
 
-impl<F: FnOnce()> Drop for Defer<F> {
+impl<F: DeferFn> Drop for Defer<F> {
     fn drop(&mut self) {
         if let Option::Some(deferred) = self.deferred.take() {
-            deferred();
+            DeferFn::call(deferred);
         }
     }
 }
 
+impl<T, E, F: FnOnce() -> Result<T, E>> Defer<F> {
+    /// Passes the error returned by the deferred closure to the handler.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use {
+    ///     keymacro::Defer,
+    ///     std::cell::RefCell,
+    /// };
+    ///
+    /// let errors = RefCell::new(Vec::new());
+    ///
+    /// {
+    ///     let _ok = Defer::new(|| Result::<i32, &str>::Ok(1));
+    ///     let failed = Defer::new(|| Result::<(), _>::Err("failed to clean up"));
+    ///     let _failed =
+    ///         Defer::with_result_handler(failed, |error| RefCell::borrow_mut(&errors).push(error));
+    /// }
+    ///
+    /// assert_eq!(*RefCell::borrow(&errors), ["failed to clean up"]);
+    /// ```
+    pub fn with_result_handler<H: FnOnce(E)>(mut self, handler: H) -> Defer<impl FnOnce()> {
+        let deferred = self.deferred.take();
+
+        Defer::new(move || {
+            if let Option::Some(deferred) = deferred {
+                if let Result::Err(error) = deferred() {
+                    handler(error);
+                }
+            }
+        })
+    }
+}
+
+impl<F: DeferFn> Debug for Defer<F> {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Defer")
+            .field("armed", &self.is_armed())
+            .finish()
+    }
+}
+
 /// Defers an evaluation.
 ///
 /// # Examples
@@ -94,34 +412,9412 @@ impl<F: FnOnce()> Drop for Defer<F> {
 ///
 /// assert!(Cell::get(&changed));
 /// ```
+///
+/// The deferred closure captures by reference by default. `move` makes it
+/// capture everything by value, and `move(...)` moves only the listed
+/// variables.
+///
+/// ```
+/// use {
+///     keymacro::defer,
+///     std::{
+///         cell::Cell,
+///         rc::Rc,
+///     },
+/// };
+///
+/// let count = Rc::new(Cell::new(0));
+///
+/// {
+///     let count = Rc::clone(&count);
+///
+///     defer!(move {
+///         Cell::set(&count, Cell::get(&count) + 1);
+///     });
+/// }
+///
+/// let mut value = 1;
+///
+/// {
+///     let count = Rc::clone(&count);
+///
+///     defer!(move(value) {
+///         Cell::set(&count, Cell::get(&count) + value);
+///     });
+///
+///     // value has been copied, so it can still be modified here.
+///     value += 1;
+/// }
+///
+/// assert_eq!(value, 2);
+/// assert_eq!(Cell::get(&count), 2);
+/// ```
+///
+/// `name =>` binds the [`Defer`] to the name, so it can be disarmed or run
+/// early.
+///
+/// ```
+/// use {
+///     keymacro::{
+///         Defer,
+///         defer,
+///     },
+///     std::cell::Cell,
+/// };
+///
+/// let count = Cell::new(0);
+///
+/// {
+///     defer!(rollback => {
+///         Cell::set(&count, Cell::get(&count) + 1);
+///     });
+///
+///     defer!(flush => {
+///         Cell::set(&count, Cell::get(&count) + 10);
+///     });
+///
+///     Defer::cancel(rollback);
+///     Defer::run_now(&mut flush);
+///     assert_eq!(Cell::get(&count), 10);
+/// }
+///
+/// assert_eq!(Cell::get(&count), 10);
+/// ```
 #[macro_export]
 macro_rules! defer {
+    (@closure move { $($token:tt)* }) => {
+        move || { $($token)* }
+    };
+    (@closure move($($name:ident),* $(,)?) { $($token:tt)* }) => {{
+        let moved = $crate::__private::Moved(($($name,)*));
+
+        || {
+            let ($($name,)*) = $crate::__private::Moved::into_inner(moved);
+            $($token)*
+        }
+    }};
+    (@closure { $($token:tt)* }) => {
+        || { $($token)* }
+    };
+    ($guard:ident => $($token:tt)+) => {
+        #[allow(unused_mut)]
+        let mut $guard = $crate::Defer::new($crate::defer!(@closure $($token)+));
+    };
+    (move $($token:tt)+) => {
+        $crate::keep!($crate::Defer::new($crate::defer!(@closure move $($token)+)));
+    };
     ($($token:tt)*) => {
         $crate::keep!($crate::Defer::new(|| { $($token)* }));
     };
 }
 
-/// A macro to write text.
+/// Defers an evaluation only in debug builds.
+///
+/// Like [`debug_assert!`], the deferred block is still type-checked in release
+/// builds but is never evaluated. No guard is created there, so the block does
+/// not keep its captures borrowed.
 ///
 /// # Examples
 ///
 /// ```
-/// use keymacro::text;
+/// use {
+///     keymacro::debug_defer,
+///     std::cell::Cell,
+/// };
 ///
-/// assert_eq!(
-///     text!(
-///         "This is the first line."
-///         "You can write more lines."
-///     ),
-///     "This is the first line.\nYou can write more lines."
-/// );
+/// let checked = Cell::new(false);
+///
+/// {
+///     debug_defer! {
+///         Cell::set(&checked, true);
+///     }
+/// }
+///
+/// assert_eq!(Cell::get(&checked), cfg!(debug_assertions));
 /// ```
 #[macro_export]
-macro_rules! text {
-    () => {
-        ""
+macro_rules! debug_defer {
+    ($($token:tt)*) => {
+        #[cfg(debug_assertions)]
+        $crate::defer! {
+            $($token)*
+        }
+
+        // No guard is kept, so nothing stays borrowed.
+        #[cfg(not(debug_assertions))]
+        if false {
+            $($token)*
+        }
     };
-    ($first:literal $($more:literal)*) => {
-        concat!($first $(, '\n', $more)*)
+}
+
+/// Defers an evaluation only if the configuration predicate holds.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::cfg_defer,
+///     std::cell::Cell,
+/// };
+///
+/// let enabled = Cell::new(false);
+/// let disabled = Cell::new(false);
+///
+/// {
+///     cfg_defer!(all(), {
+///         Cell::set(&enabled, true);
+///     });
+///
+///     cfg_defer!(any(), {
+///         Cell::set(&disabled, true);
+///     });
+/// }
+///
+/// assert!(Cell::get(&enabled));
+/// assert!(!Cell::get(&disabled));
+/// ```
+#[macro_export]
+macro_rules! cfg_defer {
+    ($predicate:meta, { $($token:tt)* } $(,)?) => {
+        #[cfg($predicate)]
+        $crate::defer! { $($token)* }
     };
 }
+
+/// A RAII owning a value and passing it to the deferred closure.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::Guard,
+///     std::cell::Cell,
+/// };
+///
+/// let sum = Cell::new(0);
+///
+/// {
+///     let mut guard = Guard::new(Vec::new(), |values: Vec<i32>| {
+///         Cell::set(&sum, values.iter().sum())
+///     });
+///
+///     guard.push(1);
+///     guard.push(2);
+///     assert_eq!(guard.len(), 2);
+///     assert_eq!(Cell::get(&sum), 0);
+/// }
+///
+/// assert_eq!(Cell::get(&sum), 3);
+/// ```
+#[must_use]
+pub struct Guard<T, F: FnOnce(T)> {
+    value: ManuallyDrop<T>,
+    deferred: ManuallyDrop<F>,
+}
+
+impl<T, F: FnOnce(T)> Guard<T, F> {
+    /// Creates a new instance.
+    pub const fn new(value: T, deferred: F) -> Self {
+        Self {
+            value: ManuallyDrop::new(value),
+            deferred: ManuallyDrop::new(deferred),
+        }
+    }
+
+    /// Disarms the guard and returns the value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use {
+    ///     keymacro::Guard,
+    ///     std::cell::Cell,
+    /// };
+    ///
+    /// let changed = Cell::new(false);
+    /// let guard = Guard::new(1, |_| Cell::set(&changed, true));
+    /// assert_eq!(Guard::into_inner(guard), 1);
+    /// assert!(!Cell::get(&changed));
+    /// ```
+    pub fn into_inner(guard: Self) -> T {
+        let mut guard = ManuallyDrop::new(guard);
+
+        // SAFETY: `guard` is never used or dropped again.
+        unsafe {
+            ManuallyDrop::drop(&mut guard.deferred);
+            ManuallyDrop::take(&mut guard.value)
+        }
+    }
+}
+
+impl<T, F: FnOnce(T)> Deref for Guard<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, F: FnOnce(T)> DerefMut for Guard<T, F> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T, F: FnOnce(T)> Drop for Guard<T, F> {
+    fn drop(&mut self) {
+        // SAFETY: `self` is never used again.
+        let (value, deferred) = unsafe {
+            (
+                ManuallyDrop::take(&mut self.value),
+                ManuallyDrop::take(&mut self.deferred),
+            )
+        };
+
+        deferred(value);
+    }
+}
+
+/// A RAII for deferring many closures.
+///
+/// The closures run in the order of their priorities, and then in the reverse
+/// order of pushing.
+///
+/// This requires the `alloc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::DeferStack,
+///     std::cell::RefCell,
+/// };
+///
+/// let order = RefCell::new(Vec::new());
+///
+/// {
+///     let mut stack = DeferStack::new();
+///
+///     for i in 0..3 {
+///         let order = &order;
+///         stack.push(move || RefCell::borrow_mut(order).push(i));
+///     }
+///
+///     assert!(RefCell::borrow(&order).is_empty());
+/// }
+///
+/// assert_eq!(*RefCell::borrow(&order), [2, 1, 0]);
+/// ```
+#[cfg(feature = "alloc")]
+#[must_use]
+#[derive(Default)]
+pub struct DeferStack<'a> {
+    deferred: Vec<(i32, Box<dyn FnOnce() + 'a>)>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> DeferStack<'a> {
+    /// Creates a new instance.
+    pub const fn new() -> Self {
+        Self {
+            deferred: Vec::new(),
+        }
+    }
+
+    /// Pushes a closure to defer.
+    pub fn push<F: FnOnce() + 'a>(&mut self, deferred: F) {
+        self.push_with_priority(0, deferred);
+    }
+
+    /// Pushes a closure to defer with a priority.
+    ///
+    /// Closures with higher priorities run first regardless of the order of
+    /// pushing. [`DeferStack::push`] uses the priority `0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use {
+    ///     keymacro::DeferStack,
+    ///     std::cell::RefCell,
+    /// };
+    ///
+    /// let order = RefCell::new(Vec::new());
+    ///
+    /// {
+    ///     let mut stack = DeferStack::new();
+    ///     stack.push_with_priority(1, || RefCell::borrow_mut(&order).push("flush"));
+    ///     stack.push(|| RefCell::borrow_mut(&order).push("close"));
+    ///     stack.push_with_priority(-1, || RefCell::borrow_mut(&order).push("log"));
+    ///     stack.push(|| RefCell::borrow_mut(&order).push("unlock"));
+    /// }
+    ///
+    /// assert_eq!(
+    ///     *RefCell::borrow(&order),
+    ///     ["flush", "unlock", "close", "log"]
+    /// );
+    /// ```
+    pub fn push_with_priority<F: FnOnce() + 'a>(&mut self, priority: i32, deferred: F) {
+        let index = self
+            .deferred
+            .partition_point(|&(other, _)| other <= priority);
+
+        self.deferred.insert(index, (priority, Box::new(deferred)));
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Drop for DeferStack<'_> {
+    fn drop(&mut self) {
+        while let Option::Some((_, deferred)) = self.deferred.pop() {
+            deferred();
+        }
+    }
+}
+
+/// A guard for deferring an asynchronous evaluation.
+///
+/// Since [`Drop`] cannot await, the deferred closure must be run by
+/// [`AsyncDefer::finish`]. Dropping an armed guard panics unless the thread is
+/// already panicking.
+///
+/// This requires the `async` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::AsyncDefer,
+///     std::{
+///         cell::Cell,
+///         future::Future,
+///         pin::pin,
+///         task::{
+///             Context,
+///             Poll,
+///             Waker,
+///         },
+///     },
+/// };
+///
+/// let changed = Cell::new(false);
+///
+/// let future = pin!(async {
+///     let defer = AsyncDefer::new(async || Cell::set(&changed, true));
+///     assert!(!Cell::get(&changed));
+///     defer.finish().await;
+/// });
+///
+/// let mut context = Context::from_waker(Waker::noop());
+/// assert_eq!(Future::poll(future, &mut context), Poll::Ready(()));
+/// assert!(Cell::get(&changed));
+/// ```
+#[cfg(feature = "async")]
+#[must_use]
+pub struct AsyncDefer<F: AsyncFnOnce()> {
+    deferred: Option<F>,
+}
+
+#[cfg(feature = "async")]
+impl<F: AsyncFnOnce()> AsyncDefer<F> {
+    /// Creates a new instance.
+    pub const fn new(deferred: F) -> Self {
+        Self {
+            deferred: Option::Some(deferred),
+        }
+    }
+
+    /// Runs the deferred closure and disarms the guard.
+    pub async fn finish(mut self) {
+        if let Option::Some(deferred) = self.deferred.take() {
+            deferred().await;
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F: AsyncFnOnce()> Drop for AsyncDefer<F> {
+    fn drop(&mut self) {
+        #[cfg(feature = "std")]
+        if std::thread::panicking() {
+            return;
+        }
+
+        if self.deferred.is_some() {
+            panic!("`AsyncDefer` was dropped without `finish`");
+        }
+    }
+}
+
+/// A RAII panicking unless it is defused.
+///
+/// The panic is skipped if the thread is already panicking, which requires the
+/// `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::DropBomb,
+///     std::panic,
+/// };
+///
+/// {
+///     let mut bomb = DropBomb::new("not defused");
+///     DropBomb::defuse(&mut bomb);
+/// }
+///
+/// let result = panic::catch_unwind(|| {
+///     let _bomb = DropBomb::new("not defused");
+/// });
+///
+/// assert!(result.is_err());
+/// ```
+#[must_use]
+pub struct DropBomb {
+    message: &'static str,
+    defused: bool,
+}
+
+impl DropBomb {
+    /// Creates a new instance.
+    pub const fn new(message: &'static str) -> Self {
+        Self {
+            message,
+            defused: false,
+        }
+    }
+
+    /// Defuses the bomb.
+    pub fn defuse(&mut self) {
+        self.defused = true;
+    }
+}
+
+impl Drop for DropBomb {
+    fn drop(&mut self) {
+        #[cfg(feature = "std")]
+        if std::thread::panicking() {
+            return;
+        }
+
+        if !self.defused {
+            panic!("{}", self.message);
+        }
+    }
+}
+
+/// A RAII for deferring only when the scope exits normally.
+///
+/// This requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::DeferOnSuccess,
+///     std::{
+///         cell::Cell,
+///         panic,
+///     },
+/// };
+///
+/// let changed = Cell::new(false);
+///
+/// {
+///     let _defer = DeferOnSuccess::new(|| Cell::set(&changed, true));
+///     assert!(!Cell::get(&changed));
+/// }
+///
+/// assert!(Cell::get(&changed));
+///
+/// let changed = Cell::new(false);
+///
+/// let _ = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+///     let _defer = DeferOnSuccess::new(|| Cell::set(&changed, true));
+///     panic!();
+/// }));
+///
+/// assert!(!Cell::get(&changed));
+/// ```
+#[cfg(feature = "std")]
+#[must_use]
+pub struct DeferOnSuccess<F: FnOnce()> {
+    deferred: Option<F>,
+}
+
+#[cfg(feature = "std")]
+impl<F: FnOnce()> DeferOnSuccess<F> {
+    /// Creates a new instance.
+    pub const fn new(deferred: F) -> Self {
+        Self {
+            deferred: Option::Some(deferred),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: FnOnce()> Drop for DeferOnSuccess<F> {
+    fn drop(&mut self) {
+        if let Option::Some(deferred) = self.deferred.take() {
+            if !std::thread::panicking() {
+                deferred();
+            }
+        }
+    }
+}
+
+/// A RAII for deferring only when the scope exits by unwinding.
+///
+/// This requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::DeferOnUnwind,
+///     std::{
+///         cell::Cell,
+///         panic,
+///     },
+/// };
+///
+/// let changed = Cell::new(false);
+///
+/// {
+///     let _defer = DeferOnUnwind::new(|| Cell::set(&changed, true));
+/// }
+///
+/// assert!(!Cell::get(&changed));
+///
+/// let _ = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+///     let _defer = DeferOnUnwind::new(|| Cell::set(&changed, true));
+///     panic!();
+/// }));
+///
+/// assert!(Cell::get(&changed));
+/// ```
+#[cfg(feature = "std")]
+#[must_use]
+pub struct DeferOnUnwind<F: FnOnce()> {
+    deferred: Option<F>,
+}
+
+#[cfg(feature = "std")]
+impl<F: FnOnce()> DeferOnUnwind<F> {
+    /// Creates a new instance.
+    pub const fn new(deferred: F) -> Self {
+        Self {
+            deferred: Option::Some(deferred),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: FnOnce()> Drop for DeferOnUnwind<F> {
+    fn drop(&mut self) {
+        if let Option::Some(deferred) = self.deferred.take() {
+            if std::thread::panicking() {
+                deferred();
+            }
+        }
+    }
+}
+
+/// Defers an evaluation until the scope exits normally.
+///
+/// This requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::defer_on_success,
+///     std::cell::Cell,
+/// };
+///
+/// let committed = Cell::new(false);
+///
+/// {
+///     defer_on_success! {
+///         Cell::set(&committed, true);
+///     }
+///
+///     assert!(!Cell::get(&committed));
+/// }
+///
+/// assert!(Cell::get(&committed));
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! defer_on_success {
+    ($($token:tt)*) => {
+        $crate::keep!($crate::DeferOnSuccess::new(|| { $($token)* }));
+    };
+}
+
+/// Defers an evaluation until the scope exits by unwinding.
+///
+/// This requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::defer_on_unwind,
+///     std::{
+///         cell::Cell,
+///         panic,
+///     },
+/// };
+///
+/// let rolled_back = Cell::new(false);
+///
+/// let _ = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+///     defer_on_unwind! {
+///         Cell::set(&rolled_back, true);
+///     }
+///
+///     panic!();
+/// }));
+///
+/// assert!(Cell::get(&rolled_back));
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! defer_on_unwind {
+    ($($token:tt)*) => {
+        $crate::keep!($crate::DeferOnUnwind::new(|| { $($token)* }));
+    };
+}
+
+/// Creates an [`AsyncDefer`] deferring an asynchronous evaluation.
+///
+/// This requires the `async` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::async_defer,
+///     std::{
+///         cell::Cell,
+///         future::Future,
+///         pin::pin,
+///         task::{
+///             Context,
+///             Poll,
+///             Waker,
+///         },
+///     },
+/// };
+///
+/// let changed = Cell::new(false);
+///
+/// let future = pin!(async {
+///     let defer = async_defer! {
+///         async {}.await;
+///         Cell::set(&changed, true);
+///     };
+///
+///     assert!(!Cell::get(&changed));
+///     defer.finish().await;
+/// });
+///
+/// let mut context = Context::from_waker(Waker::noop());
+/// assert_eq!(Future::poll(future, &mut context), Poll::Ready(()));
+/// assert!(Cell::get(&changed));
+/// ```
+#[cfg(feature = "async")]
+#[macro_export]
+macro_rules! async_defer {
+    (move { $($token:tt)* }) => {
+        $crate::AsyncDefer::new(async move || { $($token)* })
+    };
+    ($($token:tt)*) => {
+        $crate::AsyncDefer::new(async || { $($token)* })
+    };
+}
+
+/// Creates a [`DropBomb`].
+///
+/// The message defaults to a generic one.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::{
+///         DropBomb,
+///         bomb,
+///     },
+///     std::panic,
+/// };
+///
+/// struct Builder {
+///     bomb: DropBomb,
+/// }
+///
+/// impl Builder {
+///     fn finish(mut self) {
+///         DropBomb::defuse(&mut self.bomb);
+///     }
+/// }
+///
+/// Builder {
+///     bomb: bomb!("`Builder::finish` must be called"),
+/// }
+/// .finish();
+///
+/// let result = panic::catch_unwind(|| {
+///     let _builder = Builder {
+///         bomb: bomb!(),
+///     };
+/// });
+///
+/// assert!(result.is_err());
+/// ```
+#[macro_export]
+macro_rules! bomb {
+    () => {
+        $crate::DropBomb::new("`DropBomb` was dropped without being defused")
+    };
+    ($message:expr $(,)?) => {
+        $crate::DropBomb::new($message)
+    };
+}
+
+/// Evaluates a fallible block with [`errdefer!`] support.
+///
+/// The block is evaluated like a closure body, so `?` and `return` exit the
+/// block. The deferred evaluations registered by [`errdefer!`] run only if
+/// the block does not evaluate to `Ok`.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::try_scope,
+///     std::cell::Cell,
+/// };
+///
+/// let rolled_back = Cell::new(false);
+///
+/// let result: Result<(), &str> = try_scope! {
+///     errdefer! {
+///         Cell::set(&rolled_back, true);
+///     }
+///
+///     Err("failed")?;
+///     Ok(())
+/// };
+///
+/// assert_eq!(result, Err("failed"));
+/// assert!(Cell::get(&rolled_back));
+///
+/// let rolled_back = Cell::new(false);
+///
+/// let result: Result<i32, &str> = try_scope! {
+///     errdefer! {
+///         Cell::set(&rolled_back, true);
+///     }
+///
+///     Ok(1)
+/// };
+///
+/// assert_eq!(result, Ok(1));
+/// assert!(!Cell::get(&rolled_back));
+/// ```
+///
+/// The block is munched one statement at a time, so long blocks are fine.
+///
+/// ```
+/// use {
+///     keymacro::try_scope,
+///     std::{
+///         cell::RefCell,
+///         num::ParseIntError,
+///     },
+/// };
+///
+/// let log = RefCell::new(Vec::new());
+///
+/// let parse = |text: &str| -> Result<(u8, u8, u8, u16), ParseIntError> {
+///     try_scope! {
+///         log.borrow_mut().push("start");
+///         errdefer!(log.borrow_mut().push("rollback"));
+///         let text = text.trim();
+///         let mut parts = text.split(':');
+///         let red = parts.next().unwrap_or_default();
+///         let green = parts.next().unwrap_or_default();
+///         let blue = parts.next().unwrap_or_default();
+///         let alpha = parts.next().unwrap_or("255");
+///         let red = red.trim();
+///         let green = green.trim();
+///         let blue = blue.trim();
+///         let alpha = alpha.trim();
+///         log.borrow_mut().push("split");
+///         let red = red.parse::<u8>()?;
+///         log.borrow_mut().push("red");
+///         let green = green.parse::<u8>()?;
+///         log.borrow_mut().push("green");
+///         let blue = blue.parse::<u8>()?;
+///         log.borrow_mut().push("blue");
+///         let alpha = alpha.parse::<u8>()?;
+///         log.borrow_mut().push("alpha");
+///         let mut sum = 0u16;
+///
+///         for channel in [red, green, blue, alpha] {
+///             sum += u16::from(channel);
+///         }
+///
+///         if sum == 0 {
+///             log.borrow_mut().push("black");
+///         }
+///
+///         let average = sum / 4;
+///         let brightest = red.max(green).max(blue);
+///         let darkest = red.min(green).min(blue);
+///         let spread = brightest - darkest;
+///         let gray = spread == 0;
+///         let opaque = alpha == 255;
+///         let label = if gray { "gray" } else { "color" };
+///         log.borrow_mut().push(label);
+///         let label = if opaque { "opaque" } else { "translucent" };
+///         log.borrow_mut().push(label);
+///         let checksum = average + u16::from(spread);
+///         log.borrow_mut().push("done");
+///         Ok((red, green, blue, checksum))
+///     }
+/// };
+///
+/// assert_eq!(parse("10:20:30"), Ok((10, 20, 30, 98)));
+/// assert!(parse("10:x:30").is_err());
+/// assert_eq!(log.borrow().last(), Some(&"rollback"));
+/// ```
+#[macro_export]
+macro_rules! try_scope {
+    (@munch $succeeded:ident [$($statement:tt)*] [$($current:tt)*] errdefer! { $($deferred:tt)* } $(;)? $($rest:tt)*) => {
+        $crate::try_scope!(
+            @munch $succeeded
+            [
+                $($statement)*
+                $($current)*
+                $crate::defer! {
+                    if !::core::cell::Cell::get(&$succeeded) {
+                        $($deferred)*
+                    }
+                }
+            ]
+            []
+            $($rest)*
+        )
+    };
+    (@munch $succeeded:ident [$($statement:tt)*] [$($current:tt)*] errdefer!($($deferred:tt)*); $($rest:tt)*) => {
+        $crate::try_scope!(
+            @munch $succeeded
+            [$($statement)* $($current)*]
+            []
+            errdefer! { $($deferred)* }
+            $($rest)*
+        )
+    };
+    (@munch $succeeded:ident [$($statement:tt)*] [$($current:tt)*] ; $($rest:tt)*) => {
+        $crate::try_scope!(@munch $succeeded [$($statement)* $($current)* ;] [] $($rest)*)
+    };
+    // Most statements are munched whole, so the depth grows with the number of
+    // statements rather than tokens.
+    (@munch $succeeded:ident [$($statement:tt)*] [] $current:stmt; $($rest:tt)*) => {
+        $crate::try_scope!(@munch $succeeded [$($statement)* $current;] [] $($rest)*)
+    };
+    // A block-like statement is munched by tokens up to its last block.
+    (@munch $succeeded:ident [$($statement:tt)*] [$($current:tt)+] { $($block:tt)* } else $($rest:tt)*) => {
+        $crate::try_scope!(@munch $succeeded [$($statement)*] [$($current)+ { $($block)* } else] $($rest)*)
+    };
+    (@munch $succeeded:ident [$($statement:tt)*] [for $($current:tt)*] { $($block:tt)* } $next:tt $($rest:tt)*) => {
+        $crate::try_scope!(@munch $succeeded [$($statement)* for $($current)* { $($block)* }] [] $next $($rest)*)
+    };
+    (@munch $succeeded:ident [$($statement:tt)*] [if $($current:tt)*] { $($block:tt)* } $next:tt $($rest:tt)*) => {
+        $crate::try_scope!(@munch $succeeded [$($statement)* if $($current)* { $($block)* }] [] $next $($rest)*)
+    };
+    (@munch $succeeded:ident [$($statement:tt)*] [loop] { $($block:tt)* } $next:tt $($rest:tt)*) => {
+        $crate::try_scope!(@munch $succeeded [$($statement)* loop { $($block)* }] [] $next $($rest)*)
+    };
+    (@munch $succeeded:ident [$($statement:tt)*] [match $($current:tt)*] { $($block:tt)* } $next:tt $($rest:tt)*) => {
+        $crate::try_scope!(@munch $succeeded [$($statement)* match $($current)* { $($block)* }] [] $next $($rest)*)
+    };
+    (@munch $succeeded:ident [$($statement:tt)*] [while $($current:tt)*] { $($block:tt)* } $next:tt $($rest:tt)*) => {
+        $crate::try_scope!(@munch $succeeded [$($statement)* while $($current)* { $($block)* }] [] $next $($rest)*)
+    };
+    (@munch $succeeded:ident [$($statement:tt)*] [$($current:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::try_scope!(@munch $succeeded [$($statement)*] [$($current)* $token] $($rest)*)
+    };
+    (@munch $succeeded:ident [$($statement:tt)*] [$($tail:tt)*]) => {
+        (|| {
+            let $succeeded = ::core::cell::Cell::new(false);
+            $($statement)*
+            let result = { $($tail)* };
+
+            if ::core::result::Result::is_ok(&result) {
+                ::core::cell::Cell::set(&$succeeded, true);
+            }
+
+            result
+        })()
+    };
+    ($($token:tt)*) => {
+        $crate::try_scope!(@munch succeeded [] [] $($token)*)
+    };
+}
+
+/// Defers an evaluation until the enclosing [`try_scope!`] fails.
+///
+/// This macro is only meaningful as a top-level statement of a
+/// [`try_scope!`] block; anywhere else it is a compile error.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::try_scope,
+///     std::cell::Cell,
+/// };
+///
+/// let released = Cell::new(0);
+///
+/// let result: Result<(), &str> = try_scope! {
+///     errdefer! {
+///         Cell::set(&released, Cell::get(&released) + 1);
+///     }
+///
+///     errdefer!(Cell::set(&released, Cell::get(&released) + 1));
+///     Err("failed")
+/// };
+///
+/// assert_eq!(result, Err("failed"));
+/// assert_eq!(Cell::get(&released), 2);
+/// ```
+#[macro_export]
+macro_rules! errdefer {
+    ($($token:tt)*) => {
+        ::core::compile_error!("`errdefer!` must be used directly inside `try_scope!`")
+    };
+}
+
+/// Evaluates a block and then another block whatever happens.
+///
+/// The `finally` block runs after the body even if it exits by `return`,
+/// `?`, `break` or a panic. The value of the body is the value of the macro.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::try_finally,
+///     std::cell::Cell,
+/// };
+///
+/// let finished = Cell::new(false);
+///
+/// let value = try_finally! {
+///     {
+///         assert!(!Cell::get(&finished));
+///         1
+///     } finally {
+///         Cell::set(&finished, true);
+///     }
+/// };
+///
+/// assert_eq!(value, 1);
+/// assert!(Cell::get(&finished));
+///
+/// let finished = Cell::new(false);
+///
+/// let parse = || -> Result<i32, std::num::ParseIntError> {
+///     try_finally! {
+///         {
+///             let value = "x".parse::<i32>()?;
+///             Ok(value)
+///         } finally {
+///             Cell::set(&finished, true);
+///         }
+///     }
+/// };
+///
+/// assert!(parse().is_err());
+/// assert!(Cell::get(&finished));
+/// ```
+#[macro_export]
+macro_rules! try_finally {
+    ({ $($body:tt)* } finally { $($cleanup:tt)* }) => {{
+        $crate::defer! { $($cleanup)* }
+        { $($body)* }
+    }};
+}
+
+/// Rewrites top-level `#[defer] { ... }` statements in a function body into
+/// [`defer!`] guards.
+///
+/// `#[defer(move)] { ... }` moves the captured variables. The deferred blocks
+/// run in reverse order when the function returns or unwinds.
+///
+/// The guards are written with `::keymacro::defer!`. If keymacro is renamed or
+/// re-exported by another crate, give a path to a module with its `defer!` by
+/// `#[guarded(crate = path)]`.
+///
+/// This requires the `attributes` feature.
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::RefCell;
+///
+/// #[keymacro::guarded]
+/// fn run(log: &RefCell<Vec<String>>) -> usize {
+///     #[defer]
+///     {
+///         RefCell::borrow_mut(log).push(String::from("first"));
+///     }
+///
+///     let name = String::from("second");
+///
+///     #[defer(move)]
+///     {
+///         RefCell::borrow_mut(log).push(name);
+///     }
+///
+///     RefCell::borrow_mut(log).push(String::from("body"));
+///     let len = RefCell::borrow(log).len();
+///     len
+/// }
+///
+/// let log = RefCell::new(Vec::new());
+/// assert_eq!(run(&log), 1);
+/// assert_eq!(*RefCell::borrow(&log), ["body", "second", "first"]);
+///
+/// mod reexport {
+///     pub use keymacro::defer;
+/// }
+///
+/// #[keymacro::guarded(crate = reexport)]
+/// fn renamed(log: &RefCell<Vec<String>>) {
+///     #[defer]
+///     {
+///         RefCell::borrow_mut(log).push(String::from("renamed"));
+///     }
+/// }
+///
+/// renamed(&log);
+/// assert_eq!(RefCell::borrow(&log).last().unwrap(), "renamed");
+/// ```
+#[cfg(feature = "attributes")]
+pub use keymacro_macros::guarded;
+
+/// A macro to write text.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::text;
+///
+/// assert_eq!(
+///     text!(
+///         "This is the first line."
+///         "You can write more lines."
+///     ),
+///     "This is the first line.\nYou can write more lines."
+/// );
+/// ```
+///
+/// `sep` changes the separator.
+///
+/// ```
+/// use keymacro::text;
+///
+/// assert_eq!(
+///     text!(
+///         sep = ", ",
+///         "SELECT id"
+///         "name"
+///         "email"
+///     ),
+///     "SELECT id, name, email"
+/// );
+/// ```
+///
+/// `trailing_newline` appends the separator to the last line too.
+///
+/// ```
+/// use keymacro::text;
+///
+/// assert_eq!(
+///     text!(
+///         trailing_newline,
+///         "first"
+///         "second"
+///     ),
+///     "first\nsecond\n"
+/// );
+/// ```
+///
+/// `crlf` joins lines with `"\r\n"`.
+///
+/// ```
+/// use keymacro::text;
+///
+/// assert_eq!(
+///     text!(
+///         crlf,
+///         trailing_newline,
+///         "HTTP/1.1 200 OK"
+///         "Content-Length: 0"
+///     ),
+///     "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n"
+/// );
+/// ```
+///
+/// `prefix` prepends a text to every line.
+///
+/// ```
+/// use keymacro::text;
+///
+/// assert_eq!(
+///     text!(
+///         prefix = "> ",
+///         "line one"
+///         "line two"
+///     ),
+///     "> line one\n> line two"
+/// );
+/// ```
+///
+/// A line can also be any constant `&str` expression in braces. The text is then
+/// joined by const evaluation instead of [`concat!`], so it is not a literal.
+///
+/// ```
+/// use keymacro::text;
+///
+/// const NAME: &str = "keymacro";
+///
+/// const BANNER: &str = text!(
+///     { NAME }
+///     "Keyword-like macros for Rust."
+///     { env!("CARGO_PKG_NAME") }
+/// );
+///
+/// assert_eq!(BANNER, "keymacro\nKeyword-like macros for Rust.\nkeymacro");
+/// ```
+#[macro_export]
+macro_rules! text {
+    (@join [$separator:literal] [$prefix:literal] [$($trailing:ident)?]) => {
+        ""
+    };
+    (@join [$separator:literal] [$prefix:literal] [] $first:literal $($more:literal)*) => {
+        concat!($prefix, $first $(, $separator, $prefix, $more)*)
+    };
+    (@join [$separator:literal] [$prefix:literal] [trailing_newline] $first:literal $($more:literal)*) => {
+        concat!($prefix, $first $(, $separator, $prefix, $more)*, $separator)
+    };
+    (@join [$separator:literal] [$prefix:literal] [] $($line:tt)*) => {
+        $crate::text!(@const [$separator] [$prefix] [false] $($line)*)
+    };
+    (@join [$separator:literal] [$prefix:literal] [trailing_newline] $($line:tt)*) => {
+        $crate::text!(@const [$separator] [$prefix] [true] $($line)*)
+    };
+    (@const [$separator:literal] [$prefix:literal] [$trailing:literal] $($line:tt)*) => {{
+        const LINES: &[&[u8]] = &[$(::core::primitive::str::as_bytes($line)),*];
+        const PREFIX: &[u8] = ::core::primitive::str::as_bytes(concat!($prefix));
+        const SEPARATOR: &[u8] = ::core::primitive::str::as_bytes(concat!($separator));
+        const LEN: usize = $crate::__private::join(LINES, PREFIX, SEPARATOR, $trailing, &mut []);
+
+        const BYTES: [u8; LEN] = {
+            let mut bytes = [0; LEN];
+            $crate::__private::join(LINES, PREFIX, SEPARATOR, $trailing, &mut bytes);
+            bytes
+        };
+
+        const TEXT: &str = $crate::__private::to_str(&BYTES);
+        TEXT
+    }};
+    (@options [$separator:literal] [$prefix:literal] [$($trailing:ident)?] sep = $new:literal, $($rest:tt)*) => {
+        $crate::text!(@options [$new] [$prefix] [$($trailing)?] $($rest)*)
+    };
+    (@options [$separator:literal] [$prefix:literal] [$($trailing:ident)?] prefix = $new:literal, $($rest:tt)*) => {
+        $crate::text!(@options [$separator] [$new] [$($trailing)?] $($rest)*)
+    };
+    (@options [$separator:literal] [$prefix:literal] [$($trailing:ident)?] crlf, $($rest:tt)*) => {
+        $crate::text!(@options ["\r\n"] [$prefix] [$($trailing)?] $($rest)*)
+    };
+    (@options [$separator:literal] [$prefix:literal] [$($trailing:ident)?] trailing_newline, $($rest:tt)*) => {
+        $crate::text!(@options [$separator] [$prefix] [trailing_newline] $($rest)*)
+    };
+    (@options [$separator:literal] [$prefix:literal] [$($trailing:ident)?] $($line:tt)*) => {
+        $crate::text!(@join [$separator] [$prefix] [$($trailing)?] $($line)*)
+    };
+    ($($token:tt)*) => {
+        $crate::text!(@options ['\n'] [""] [] $($token)*)
+    };
+}
+
+/// A macro to write formatted text.
+///
+/// The lines are joined like [`text!`] and formatted like [`format_args!`].
+/// Without arguments, each line can capture variables by name. With arguments,
+/// the whole text is one format string.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::ftext;
+///
+/// let name = "Rust";
+///
+/// assert_eq!(
+///     format!("{}", ftext!("Hello, {name}!" "Bye, {name}!")),
+///     "Hello, Rust!\nBye, Rust!"
+/// );
+///
+/// assert_eq!(
+///     format!("{}", ftext!("{} + {}" "= {}", 1, 2, 1 + 2)),
+///     "1 + 2\n= 3"
+/// );
+/// ```
+#[macro_export]
+macro_rules! ftext {
+    () => {
+        ::core::format_args!("")
+    };
+    ($first:literal) => {
+        ::core::format_args!($first)
+    };
+    ($first:literal $($more:literal)+) => {
+        ::core::format_args!(
+            "{}\n{}",
+            ::core::format_args!($first),
+            $crate::ftext!($($more)+)
+        )
+    };
+    ($($line:literal)+, $($argument:tt)*) => {
+        ::core::format_args!($crate::text!($($line)+), $($argument)*)
+    };
+}
+
+/// A macro to write an array of lines.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::lines;
+///
+/// const LINES: [&str; 2] = lines!(
+///     "This is the first line."
+///     "You can write more lines."
+/// );
+///
+/// assert_eq!(
+///     LINES,
+///     ["This is the first line.", "You can write more lines."]
+/// );
+/// ```
+#[macro_export]
+macro_rules! lines {
+    ($($line:literal)*) => {
+        [$($line),*]
+    };
+}
+
+/// A macro to write byte text.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::btext;
+///
+/// const FRAME: &[u8; 8] = btext!(
+///     b"GET"
+///     b"\x00\x01"
+///     b"/"
+/// );
+///
+/// assert_eq!(FRAME, b"GET\n\x00\x01\n/");
+/// ```
+#[macro_export]
+macro_rules! btext {
+    ($($line:literal)*) => {{
+        const LINES: &[&[u8]] = &[$($line),*];
+        const LEN: usize = $crate::__private::join(LINES, &[], b"\n", false, &mut []);
+
+        const BYTES: [u8; LEN] = {
+            let mut bytes = [0; LEN];
+            $crate::__private::join(LINES, &[], b"\n", false, &mut bytes);
+            bytes
+        };
+
+        &BYTES
+    }};
+}
+
+/// Creates a `&'static CStr` from a string literal.
+///
+/// An interior NUL is a compile error.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::cstr,
+///     std::ffi::CStr,
+/// };
+///
+/// const HELLO: &CStr = cstr!("hello");
+/// assert_eq!(HELLO.to_bytes_with_nul(), b"hello\0");
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::cstr;
+///
+/// let _ = cstr!("hel\0lo");
+/// ```
+#[macro_export]
+macro_rules! cstr {
+    ($text:literal $(,)?) => {{
+        const TEXT: &::core::ffi::CStr = $crate::__private::to_c_str(concat!($text, "\0"));
+        TEXT
+    }};
+}
+
+/// Creates a `&'static [u16]` encoding a string literal in UTF-16.
+///
+/// `nul` appends a NUL terminator.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::utf16;
+///
+/// const TEXT: &[u16] = utf16!("a😀");
+/// assert_eq!(TEXT, "a😀".encode_utf16().collect::<Vec<_>>());
+///
+/// const PATH: &[u16] = utf16!(nul, "C:");
+/// assert_eq!(PATH, [0x43, 0x3A, 0]);
+/// ```
+#[macro_export]
+macro_rules! utf16 {
+    (@encode $text:expr, $nul:literal) => {{
+        const LEN: usize = $crate::__private::encode_utf16($text, $nul, &mut []);
+
+        const UNITS: [u16; LEN] = {
+            let mut units = [0; LEN];
+            $crate::__private::encode_utf16($text, $nul, &mut units);
+            units
+        };
+
+        &UNITS
+    }};
+    (nul, $text:expr $(,)?) => {
+        $crate::utf16!(@encode $text, true)
+    };
+    ($text:expr $(,)?) => {
+        $crate::utf16!(@encode $text, false)
+    };
+}
+
+/// Asserts that a string is ASCII at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::ascii;
+///
+/// const NAME: &str = ascii!("content-type");
+/// assert_eq!(NAME, "content-type");
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::ascii;
+///
+/// let _ = ascii!("café");
+/// ```
+#[macro_export]
+macro_rules! ascii {
+    ($text:expr $(,)?) => {{
+        const TEXT: &str = $crate::__private::assert_ascii($text);
+        TEXT
+    }};
+}
+
+/// Concatenates byte literals into a `&'static [u8; N]`.
+///
+/// Each part is a byte string literal, a byte literal or an array of bytes.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::concat_bytes;
+///
+/// const BYTES: &[u8; 7] = concat_bytes!(b"ab", b'c', [0x01, 0x02], [0; 2]);
+/// assert_eq!(BYTES, b"abc\x01\x02\x00\x00");
+/// ```
+#[macro_export]
+macro_rules! concat_bytes {
+    (@part [$($byte:expr),* $(,)?]) => {
+        &[$($byte),*]
+    };
+    (@part [$byte:expr; $len:expr]) => {
+        &[$byte; $len]
+    };
+    (@part $part:literal) => {
+        $crate::__private::Bytes($part).as_bytes()
+    };
+    ($($part:tt),* $(,)?) => {{
+        const PARTS: &[&[u8]] = &[$($crate::concat_bytes!(@part $part)),*];
+        const LEN: usize = $crate::__private::join(PARTS, &[], &[], false, &mut []);
+
+        const BYTES: [u8; LEN] = {
+            let mut bytes = [0; LEN];
+            $crate::__private::join(PARTS, &[], &[], false, &mut bytes);
+            bytes
+        };
+
+        &BYTES
+    }};
+}
+
+/// Decodes a hexadecimal string into a byte array at compile time.
+///
+/// ASCII whitespace is ignored. An invalid digit or an odd number of digits is a
+/// compile error.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::hex;
+///
+/// const BYTES: [u8; 4] = hex!("deadBEEF");
+/// assert_eq!(BYTES, [0xDE, 0xAD, 0xBE, 0xEF]);
+/// assert_eq!(hex!("00 01 02"), [0, 1, 2]);
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::hex;
+///
+/// let _ = hex!("abc");
+/// ```
+#[macro_export]
+macro_rules! hex {
+    ($text:expr $(,)?) => {{
+        const LEN: usize = $crate::__private::decode_hex($text, &mut []);
+
+        const BYTES: [u8; LEN] = {
+            let mut bytes = [0; LEN];
+            $crate::__private::decode_hex($text, &mut bytes);
+            bytes
+        };
+
+        BYTES
+    }};
+}
+
+/// Decodes a base64 string into a `&'static [u8; N]` at compile time.
+///
+/// The standard alphabet is used, the padding is optional and ASCII whitespace
+/// is ignored. Invalid input is a compile error.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::base64;
+///
+/// const BYTES: &[u8; 5] = base64!("aGVsbG8=");
+/// assert_eq!(BYTES, b"hello");
+/// assert_eq!(base64!("AAEC/w"), &[0, 1, 2, 255]);
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::base64;
+///
+/// let _ = base64!("a*==");
+/// ```
+#[macro_export]
+macro_rules! base64 {
+    ($text:expr $(,)?) => {{
+        const LEN: usize = $crate::__private::decode_base64($text, &mut []);
+
+        const BYTES: [u8; LEN] = {
+            let mut bytes = [0; LEN];
+            $crate::__private::decode_base64($text, &mut bytes);
+            bytes
+        };
+
+        &BYTES
+    }};
+}
+
+/// Removes the common indentation of lines at compile time.
+///
+/// An empty first line is removed, so the text can start on the line after the
+/// opening quote. Lines containing only whitespace are ignored when measuring
+/// the indentation and become empty.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::dedent;
+///
+/// assert_eq!(
+///     dedent!(
+///         "
+///         fn main() {
+///             println!();
+///         }
+///         "
+///     ),
+///     "fn main() {\n    println!();\n}\n"
+/// );
+/// ```
+#[macro_export]
+macro_rules! dedent {
+    ($text:expr $(,)?) => {{
+        const LEN: usize = $crate::__private::dedent($text, &mut []);
+
+        const BYTES: [u8; LEN] = {
+            let mut bytes = [0; LEN];
+            $crate::__private::dedent($text, &mut bytes);
+            bytes
+        };
+
+        const TEXT: &str = $crate::__private::to_str(&BYTES);
+        TEXT
+    }};
+}
+
+/// A macro to write a text from environment variables at compile time.
+///
+/// Each line is the value of the environment variable named by the literal,
+/// read by [`env!`]. The options of [`text!`] come first.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::env_text;
+///
+/// const BANNER: &str = env_text!(
+///     sep = " ",
+///     "CARGO_PKG_NAME"
+///     "CARGO_PKG_VERSION"
+/// );
+///
+/// assert_eq!(
+///     BANNER,
+///     concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"))
+/// );
+/// ```
+#[macro_export]
+macro_rules! env_text {
+    (@munch [$($output:tt)*] sep = $separator:literal, $($rest:tt)*) => {
+        $crate::env_text!(@munch [$($output)* sep = $separator,] $($rest)*)
+    };
+    (@munch [$($output:tt)*] prefix = $prefix:literal, $($rest:tt)*) => {
+        $crate::env_text!(@munch [$($output)* prefix = $prefix,] $($rest)*)
+    };
+    (@munch [$($output:tt)*] crlf, $($rest:tt)*) => {
+        $crate::env_text!(@munch [$($output)* crlf,] $($rest)*)
+    };
+    (@munch [$($output:tt)*] trailing_newline, $($rest:tt)*) => {
+        $crate::env_text!(@munch [$($output)* trailing_newline,] $($rest)*)
+    };
+    (@munch [$($output:tt)*] $name:literal $($rest:tt)*) => {
+        $crate::env_text!(@munch [$($output)* { ::core::env!($name) }] $($rest)*)
+    };
+    (@munch [$($output:tt)*]) => {
+        $crate::text!($($output)*)
+    };
+    ($($token:tt)*) => {
+        $crate::env_text!(@munch [] $($token)*)
+    };
+}
+
+/// A macro to include a file as a text at compile time.
+///
+/// It reads the file like [`include_str!`] and joins its lines like [`text!`],
+/// so the line endings become the separator and the trailing newline is
+/// removed. The options of [`text!`] come first.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::include_text;
+///
+/// const LICENSE: &str = include_text!(crlf, trailing_newline, "../LICENSE.txt");
+///
+/// assert!(LICENSE.starts_with("MIT License\r\n"));
+/// assert!(LICENSE.ends_with("SOFTWARE.\r\n"));
+/// assert!(!LICENSE.contains("\r\n\n"));
+/// ```
+#[macro_export]
+macro_rules! include_text {
+    (@options [$separator:literal] [$prefix:literal] [$trailing:literal] sep = $new:literal, $($rest:tt)*) => {
+        $crate::include_text!(@options [$new] [$prefix] [$trailing] $($rest)*)
+    };
+    (@options [$separator:literal] [$prefix:literal] [$trailing:literal] prefix = $new:literal, $($rest:tt)*) => {
+        $crate::include_text!(@options [$separator] [$new] [$trailing] $($rest)*)
+    };
+    (@options [$separator:literal] [$prefix:literal] [$trailing:literal] crlf, $($rest:tt)*) => {
+        $crate::include_text!(@options ["\r\n"] [$prefix] [$trailing] $($rest)*)
+    };
+    (@options [$separator:literal] [$prefix:literal] [$trailing:literal] trailing_newline, $($rest:tt)*) => {
+        $crate::include_text!(@options [$separator] [$prefix] [true] $($rest)*)
+    };
+    (@options [$separator:literal] [$prefix:literal] [$trailing:literal] $path:expr $(,)?) => {{
+        const INPUT: &str = ::core::include_str!($path);
+        const PREFIX: &[u8] = ::core::primitive::str::as_bytes(concat!($prefix));
+        const SEPARATOR: &[u8] = ::core::primitive::str::as_bytes(concat!($separator));
+        const LEN: usize = $crate::__private::normalize_lines(INPUT, PREFIX, SEPARATOR, $trailing, &mut []);
+
+        const BYTES: [u8; LEN] = {
+            let mut bytes = [0; LEN];
+            $crate::__private::normalize_lines(INPUT, PREFIX, SEPARATOR, $trailing, &mut bytes);
+            bytes
+        };
+
+        const TEXT: &str = $crate::__private::to_str(&BYTES);
+        TEXT
+    }};
+    ($($token:tt)*) => {
+        $crate::include_text!(@options ['\n'] [""] [false] $($token)*)
+    };
+}
+
+/// A macro to write a documentation string for `#[doc = ...]`.
+///
+/// Lines are literals or macro calls expanding to literals, such as [`env!`]
+/// and [`concat!`], and they are joined with newlines. `;` starts a new
+/// paragraph by inserting a blank line. Since it expands to [`concat!`] to be
+/// usable in attributes, constants cannot be used.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::doc_text;
+///
+/// #[doc = doc_text!(
+///     "Returns the package name."
+///     "It is read at compile time.";
+///     concat!("The name is `", env!("CARGO_PKG_NAME"), "`.")
+/// )]
+/// pub fn name() -> &'static str {
+///     env!("CARGO_PKG_NAME")
+/// }
+///
+/// assert_eq!(doc_text!("first" "second"; "third"), "first\nsecond\n\nthird");
+/// ```
+#[macro_export]
+macro_rules! doc_text {
+    (@munch [$($output:tt)*] [] ; $($rest:tt)*) => {
+        $crate::doc_text!(@munch [$($output)*] [] $($rest)*)
+    };
+    (@munch [$($output:tt)*] [$separator:literal] ; $($rest:tt)*) => {
+        $crate::doc_text!(@munch [$($output)*] ["\n\n"] $($rest)*)
+    };
+    (@munch [$($output:tt)*] [$($separator:literal)?] $line:literal $($rest:tt)*) => {
+        $crate::doc_text!(@munch [$($output)* $($separator,)? $line,] ["\n"] $($rest)*)
+    };
+    (
+        @munch [$($output:tt)*] [$($separator:literal)?]
+        $name:ident!($($argument:tt)*) $($rest:tt)*
+    ) => {
+        $crate::doc_text!(@munch [$($output)* $($separator,)? $name!($($argument)*),] ["\n"] $($rest)*)
+    };
+    (@munch [$($output:tt)*] [$($separator:literal)?]) => {
+        ::core::concat!($($output)*)
+    };
+    ($($token:tt)*) => {
+        $crate::doc_text!(@munch [] [] $($token)*)
+    };
+}
+
+/// Implements [`Display`](core::fmt::Display) with a template of lines.
+///
+/// The lines are joined and formatted like [`ftext!`]. The listed fields are
+/// bound by name, so each line can capture them.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::display_from_text;
+///
+/// struct Status {
+///     name: &'static str,
+///     count: usize,
+///     ratio: f64,
+/// }
+///
+/// display_from_text! {
+///     Status { name, count, ratio };
+///     "Name: {name}"
+///     "Count: {count:>3}"
+///     "Ratio: {ratio:.2}"
+/// }
+///
+/// let status = Status {
+///     name: "build",
+///     count: 7,
+///     ratio: 0.5,
+/// };
+///
+/// assert_eq!(status.to_string(), "Name: build\nCount:   7\nRatio: 0.50");
+/// ```
+#[macro_export]
+macro_rules! display_from_text {
+    ($type:ty { $($field:ident),* $(,)? }; $($line:literal)+) => {
+        impl ::core::fmt::Display for $type {
+            fn fmt(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                let Self { $($field,)* .. } = self;
+                ::core::fmt::Formatter::write_fmt(formatter, $crate::ftext!($($line)+))
+            }
+        }
+    };
+}
+
+/// A macro to write a path from segments.
+///
+/// The segments are joined with `'/'` like [`text!`], and `sep` changes the
+/// separator.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::path_text;
+///
+/// const ROOT: &str = "api";
+///
+/// assert_eq!(path_text!("api" "v1" "users"), "api/v1/users");
+/// assert_eq!(path_text!({ ROOT } "v2"), "api/v2");
+/// assert_eq!(path_text!(sep = '\\', "C:" "app" "app.conf"), r"C:\app\app.conf");
+/// ```
+#[macro_export]
+macro_rules! path_text {
+    ($($token:tt)*) => {
+        $crate::text!(sep = '/', $($token)*)
+    };
+}
+
+/// Selects a text by `cfg` predicates at compile time.
+///
+/// The first text whose predicate holds is selected like [`cfg_match!`], and
+/// `_` matches when no predicate holds.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::platform_text;
+///
+/// const CONFIG: &str = platform_text!(
+///     unix => "/etc/app.conf",
+///     windows => r"C:\app\app.conf",
+///     _ => "app.conf",
+/// );
+///
+/// #[cfg(unix)]
+/// assert_eq!(CONFIG, "/etc/app.conf");
+///
+/// const LINE_ENDING: &str = platform_text!(windows => "\r\n", _ => "\n");
+/// assert!(LINE_ENDING.ends_with('\n'));
+/// ```
+#[macro_export]
+macro_rules! platform_text {
+    (@munch [$($arm:tt)*] $(,)?) => {
+        $crate::cfg_match! {{ $($arm)* }}
+    };
+    (@munch [$($arm:tt)*] _ => $text:literal $(,)?) => {
+        $crate::platform_text!(@munch [$($arm)* _ => { $text }])
+    };
+    (@munch [$($arm:tt)*] $predicate:meta => $text:literal $(, $($rest:tt)*)?) => {
+        $crate::platform_text!(@munch [$($arm)* cfg($predicate) => { $text },] $($($rest)*)?)
+    };
+    ($($token:tt)*) => {
+        $crate::platform_text!(@munch [] $($token)*)
+    };
+}
+
+/// A macro to repeat a text a number of times at compile time.
+///
+/// The text can be a literal or any constant `&str` expression in braces, and
+/// the count is a constant `usize` expression.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::repeat_text;
+///
+/// const RULE: &str = repeat_text!("-", 40);
+/// const WIDTH: usize = 3;
+///
+/// assert_eq!(RULE.len(), 40);
+/// assert!(RULE.bytes().all(|byte| byte == b'-'));
+/// assert_eq!(repeat_text!("ab", WIDTH), "ababab");
+/// assert_eq!(
+///     repeat_text!({ env!("CARGO_PKG_NAME") }, 2),
+///     "keymacrokeymacro"
+/// );
+/// assert_eq!(repeat_text!("x", 0), "");
+/// ```
+#[macro_export]
+macro_rules! repeat_text {
+    ($text:literal, $count:expr $(,)?) => {
+        $crate::repeat_text!({ $text }, $count)
+    };
+    ({ $text:expr }, $count:expr $(,)?) => {{
+        const TEXT: &[u8] = ::core::primitive::str::as_bytes($text);
+        const COUNT: usize = $count;
+        const LEN: usize = $crate::__private::repeat(TEXT, COUNT, &mut []);
+
+        const BYTES: [u8; LEN] = {
+            let mut bytes = [0; LEN];
+            $crate::__private::repeat(TEXT, COUNT, &mut bytes);
+            bytes
+        };
+
+        const REPEATED: &str = $crate::__private::to_str(&BYTES);
+        REPEATED
+    }};
+}
+
+/// A macro to pad a text to a width at compile time.
+///
+/// The width is counted in characters, and a text longer than the width is a
+/// compile error. The options before the text are:
+///
+/// - `right` aligns the text to the right, and `center` centers it, with any
+///   extra fill on the right. The text is aligned to the left by default.
+/// - `fill = 'c'` changes the fill character from `' '`.
+///
+/// The text can be a literal or any constant `&str` expression in braces.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::pad_text;
+///
+/// assert_eq!(pad_text!("NAME", 8), "NAME    ");
+/// assert_eq!(pad_text!(right, "42", 6), "    42");
+/// assert_eq!(pad_text!(right, fill = '0', "42", 6), "000042");
+/// assert_eq!(pad_text!(center, fill = '·', "ab", 5), "·ab··");
+/// assert_eq!(pad_text!({ env!("CARGO_PKG_NAME") }, 10), "keymacro  ");
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::pad_text;
+///
+/// const NAME: &str = pad_text!("NAME", 3);
+/// ```
+#[macro_export]
+macro_rules! pad_text {
+    (@options [$align:ident] [$fill:literal] right, $($rest:tt)*) => {
+        $crate::pad_text!(@options [Right] [$fill] $($rest)*)
+    };
+    (@options [$align:ident] [$fill:literal] center, $($rest:tt)*) => {
+        $crate::pad_text!(@options [Center] [$fill] $($rest)*)
+    };
+    (@options [$align:ident] [$fill:literal] fill = $new:literal, $($rest:tt)*) => {
+        $crate::pad_text!(@options [$align] [$new] $($rest)*)
+    };
+    (@options [$align:ident] [$fill:literal] $text:literal, $width:expr $(,)?) => {
+        $crate::pad_text!(@options [$align] [$fill] { $text }, $width)
+    };
+    (@options [$align:ident] [$fill:literal] { $text:expr }, $width:expr $(,)?) => {{
+        const TEXT: &[u8] = ::core::primitive::str::as_bytes($text);
+        const WIDTH: usize = $width;
+        const ALIGN: $crate::__private::Align = $crate::__private::Align::$align;
+        const LEN: usize = $crate::__private::pad(TEXT, WIDTH, $fill, ALIGN, &mut []);
+
+        const BYTES: [u8; LEN] = {
+            let mut bytes = [0; LEN];
+            $crate::__private::pad(TEXT, WIDTH, $fill, ALIGN, &mut bytes);
+            bytes
+        };
+
+        const PADDED: &str = $crate::__private::to_str(&BYTES);
+        PADDED
+    }};
+    ($($token:tt)*) => {
+        $crate::pad_text!(@options [Left] [' '] $($token)*)
+    };
+}
+
+/// A macro to write a table with aligned columns at compile time.
+///
+/// Each row is a list of cells in brackets. The columns are padded to the
+/// widest cell in characters and joined with two spaces, or the separator
+/// given with `sep`, and the rows are joined with `'\n'`. The last cell of a
+/// row is not padded. A cell can be a literal or any constant `&str`
+/// expression in braces.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::table;
+///
+/// const USAGE: &str = table! {
+///     ["Command", "Description"]
+///     ["help", "Shows the help"]
+///     ["run", "Runs the program", "(default)"]
+/// };
+///
+/// assert_eq!(
+///     USAGE,
+///     concat!(
+///         "Command  Description\n",
+///         "help     Shows the help\n",
+///         "run      Runs the program  (default)",
+///     )
+/// );
+///
+/// assert_eq!(table!(sep = " | ", ["a", "b"] ["ccc", "d"]), "a   | b\nccc | d");
+/// ```
+#[macro_export]
+macro_rules! table {
+    (@cell $cell:literal) => {
+        ::core::primitive::str::as_bytes($cell)
+    };
+    (@cell { $cell:expr }) => {
+        ::core::primitive::str::as_bytes($cell)
+    };
+    (sep = $separator:literal, $([$($cell:tt),* $(,)?])*) => {{
+        const ROWS: &[&[&[u8]]] = &[$(&[$($crate::table!(@cell $cell)),*]),*];
+        const SEPARATOR: &[u8] = ::core::primitive::str::as_bytes($separator);
+        const LEN: usize = $crate::__private::table(ROWS, SEPARATOR, &mut []);
+
+        const BYTES: [u8; LEN] = {
+            let mut bytes = [0; LEN];
+            $crate::__private::table(ROWS, SEPARATOR, &mut bytes);
+            bytes
+        };
+
+        const TABLE: &str = $crate::__private::to_str(&BYTES);
+        TABLE
+    }};
+    ($($row:tt)*) => {
+        $crate::table!(sep = "  ", $($row)*)
+    };
+}
+
+/// A macro to convert a text to ASCII uppercase at compile time.
+///
+/// The text can be a literal or any constant `&str` expression in braces.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::upper;
+///
+/// const HEADER: &str = "content-type";
+///
+/// assert_eq!(upper!("content-type"), "CONTENT-TYPE");
+/// assert_eq!(upper!({ HEADER }), "CONTENT-TYPE");
+/// assert_eq!(upper!("grüße"), "GRüßE");
+/// ```
+#[macro_export]
+macro_rules! upper {
+    ($text:literal) => {
+        $crate::upper!({ $text })
+    };
+    ({ $text:expr }) => {{
+        const TEXT: &[u8] = ::core::primitive::str::as_bytes($text);
+        const BYTES: [u8; TEXT.len()] = $crate::__private::ascii_case(TEXT, true);
+        const UPPER: &str = $crate::__private::to_str(&BYTES);
+        UPPER
+    }};
+}
+
+/// A macro to convert a text to ASCII lowercase at compile time.
+///
+/// The text can be a literal or any constant `&str` expression in braces.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::lower;
+///
+/// assert_eq!(lower!("SELECT"), "select");
+/// assert_eq!(lower!({ env!("CARGO_PKG_NAME") }), "keymacro");
+/// ```
+#[macro_export]
+macro_rules! lower {
+    ($text:literal) => {
+        $crate::lower!({ $text })
+    };
+    ({ $text:expr }) => {{
+        const TEXT: &[u8] = ::core::primitive::str::as_bytes($text);
+        const BYTES: [u8; TEXT.len()] = $crate::__private::ascii_case(TEXT, false);
+        const LOWER: &str = $crate::__private::to_str(&BYTES);
+        LOWER
+    }};
+}
+
+/// A macro to write JSON text, validated at compile time.
+///
+/// The lines are joined like [`text!`], with the same options, and the
+/// result must be valid JSON.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::json_text;
+///
+/// const FIXTURE: &str = json_text!(
+///     r#"{"#
+///     r#"  "name": "keymacro","#
+///     r#"  "tags": ["macros", "no_std"],"#
+///     r#"  "version": { "major": 0, "minor": 2.5e1 },"#
+///     r#"  "stable": false, "parent": null"#
+///     r#"}"#
+/// );
+///
+/// assert!(FIXTURE.starts_with("{\n  \"name\""));
+/// assert_eq!(json_text!(sep = " ", "[1," "2]"), "[1, 2]");
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::json_text;
+///
+/// const FIXTURE: &str = json_text!(r#"{ "name": "keymacro", }"#);
+/// ```
+#[macro_export]
+macro_rules! json_text {
+    ($($token:tt)*) => {{
+        const JSON: &str = $crate::text!($($token)*);
+
+        const _: () = ::core::assert!(
+            $crate::__private::is_json(::core::primitive::str::as_bytes(JSON)),
+            "invalid JSON"
+        );
+
+        JSON
+    }};
+}
+
+/// A macro to write a query on one line at compile time.
+///
+/// The lines are joined with spaces, and each run of whitespace outside of
+/// single-quoted strings and double-quoted identifiers is collapsed into one
+/// space, with none at either end. A `--` comment is removed up to the end of
+/// its line, so it never comments out the following lines. A line can be a
+/// literal or any constant `&str` expression in braces.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::sql_text;
+///
+/// const TABLE: &str = "users";
+///
+/// assert_eq!(
+///     sql_text!(
+///         "SELECT id, name"
+///         "FROM users"
+///         "WHERE id = ?1"
+///     ),
+///     "SELECT id, name FROM users WHERE id = ?1"
+/// );
+///
+/// assert_eq!(
+///     sql_text!(
+///         "
+///         SELECT *
+///             FROM"
+///         { TABLE }
+///         "WHERE name = 'a  b'  "
+///     ),
+///     "SELECT * FROM users WHERE name = 'a  b'"
+/// );
+///
+/// assert_eq!(
+///     sql_text!(
+///         "SELECT id -- primary key"
+///         "FROM \"user  table\" WHERE note = '--'"
+///     ),
+///     "SELECT id FROM \"user  table\" WHERE note = '--'"
+/// );
+/// ```
+#[macro_export]
+macro_rules! sql_text {
+    (@line $line:literal) => {
+        ::core::primitive::str::as_bytes($line)
+    };
+    (@line { $line:expr }) => {
+        ::core::primitive::str::as_bytes($line)
+    };
+    ($($line:tt)*) => {{
+        const LINES: &[&[u8]] = &[$($crate::sql_text!(@line $line)),*];
+        const LEN: usize = $crate::__private::squish(LINES, &mut []);
+
+        const BYTES: [u8; LEN] = {
+            let mut bytes = [0; LEN];
+            $crate::__private::squish(LINES, &mut bytes);
+            bytes
+        };
+
+        const SQL: &str = $crate::__private::to_str(&BYTES);
+        SQL
+    }};
+}
+
+/// Pastes identifiers.
+///
+/// Tokens in `[< ... >]` are concatenated into one identifier. A segment can be
+/// an identifier, a literal or a `macro_rules!` fragment, followed by `:lower`
+/// or `:upper` to change its case. A result that is not an identifier fails the
+/// build.
+///
+/// This requires the `paste` feature.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::paste;
+///
+/// macro_rules! handler {
+///     ($name:ident) => {
+///         paste! {
+///             fn [<handle_ $name>]() -> &'static str {
+///                 stringify!($name)
+///             }
+///
+///             const [<$name:upper _ID>]: u32 = 1;
+///         }
+///     };
+/// }
+///
+/// handler!(click);
+/// assert_eq!(handle_click(), "click");
+/// assert_eq!(CLICK_ID, 1);
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::paste;
+///
+/// paste! {
+///     fn [<handle "-click">]() {}
+/// }
+/// ```
+#[cfg(feature = "paste")]
+pub use keymacro_macros::paste;
+
+/// Asserts a constant condition at compile time.
+///
+/// This can be used wherever an item can be.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::static_assert;
+///
+/// static_assert!(u32::BITS == 32);
+/// static_assert!(size_of::<u64>() == 8, "u64 must be 8 bytes");
+///
+/// fn check() {
+///     static_assert!(i8::MIN < 0);
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::static_assert;
+///
+/// static_assert!(1 + 1 == 3, "math is broken");
+/// ```
+#[macro_export]
+macro_rules! static_assert {
+    ($condition:expr $(,)?) => {
+        const _: () = ::core::assert!($condition);
+    };
+    ($condition:expr, $message:literal $(,)?) => {
+        const _: () = ::core::assert!($condition, $message);
+    };
+}
+
+/// A `for` loop over a range usable in const contexts.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::const_for;
+///
+/// const fn squares<const N: usize>() -> [usize; N] {
+///     let mut squares = [0; N];
+///
+///     const_for!(i in 0..N {
+///         squares[i] = i * i;
+///     });
+///
+///     squares
+/// }
+///
+/// const SQUARES: [usize; 4] = squares();
+/// assert_eq!(SQUARES, [0, 1, 4, 9]);
+///
+/// const SUM: u8 = {
+///     let mut sum = 0;
+///
+///     const_for!(i in 250..=u8::MAX {
+///         if i % 2 == 0 {
+///             continue;
+///         }
+///
+///         sum += i - 250;
+///     });
+///
+///     sum
+/// };
+///
+/// assert_eq!(SUM, 1 + 3 + 5);
+/// ```
+#[macro_export]
+macro_rules! const_for {
+    (@start $name:ident [$($start:tt)*] ..= $($rest:tt)*) => {
+        $crate::const_for!(@end $name [$($start)*] [=] [] $($rest)*)
+    };
+    (@start $name:ident [$($start:tt)*] .. $($rest:tt)*) => {
+        $crate::const_for!(@end $name [$($start)*] [] [] $($rest)*)
+    };
+    (@start $name:ident [$($start:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::const_for!(@start $name [$($start)* $token] $($rest)*)
+    };
+    (@end $name:ident [$($start:tt)*] [] [$($end:tt)*] { $($body:tt)* }) => {{
+        let mut index = $($start)*;
+        let end = $($end)*;
+
+        while index < end {
+            let $name = index;
+            index += 1;
+            { $($body)* }
+        }
+    }};
+    (@end $name:ident [$($start:tt)*] [=] [$($end:tt)*] { $($body:tt)* }) => {{
+        let mut index = $($start)*;
+        let end = $($end)*;
+        let mut done = index > end;
+
+        while !done {
+            let $name = index;
+            done = index == end;
+
+            if !done {
+                index += 1;
+            }
+
+            { $($body)* }
+        }
+    }};
+    (@end $name:ident [$($start:tt)*] [$($inclusive:tt)?] [$($end:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::const_for!(@end $name [$($start)*] [$($inclusive)?] [$($end)* $token] $($rest)*)
+    };
+    ($name:ident in $($rest:tt)*) => {
+        $crate::const_for!(@start $name [] $($rest)*)
+    };
+}
+
+/// Hints that a condition is likely to be true.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::likely;
+///
+/// let value = 1;
+///
+/// if likely!(value > 0) {
+///     println!("positive");
+/// }
+/// ```
+#[macro_export]
+macro_rules! likely {
+    ($condition:expr $(,)?) => {
+        $crate::__private::likely($condition)
+    };
+}
+
+/// Hints that a condition is unlikely to be true.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::unlikely;
+///
+/// let value = 1;
+///
+/// if unlikely!(value < 0) {
+///     panic!("negative");
+/// }
+/// ```
+#[macro_export]
+macro_rules! unlikely {
+    ($condition:expr $(,)?) => {
+        $crate::__private::unlikely($condition)
+    };
+}
+
+/// Evaluates a block as a cold path.
+///
+/// The block is evaluated in a `#[cold]` function which is never inlined, so
+/// `return`, `?` and `break` in it cannot exit the enclosing function or loop.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::cold;
+///
+/// fn parse(byte: u8) -> Result<u8, String> {
+///     match byte {
+///         b'0'..=b'9' => Ok(byte - b'0'),
+///         _ => cold! {
+///             Err(format!("invalid digit: {byte}"))
+///         },
+///     }
+/// }
+///
+/// assert_eq!(parse(b'7'), Ok(7));
+/// assert!(parse(b'x').is_err());
+/// ```
+#[macro_export]
+macro_rules! cold {
+    ($($token:tt)*) => {
+        $crate::__private::cold_path(|| { $($token)* })
+    };
+}
+
+/// Clones variables before evaluating an expression, typically a closure.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::clone,
+///     std::rc::Rc,
+/// };
+///
+/// let name = Rc::new(String::from("Rust"));
+/// let greet = clone!(name => move |greeting: &str| format!("{greeting}, {name}!"));
+///
+/// assert_eq!(greet("Hello"), "Hello, Rust!");
+/// assert_eq!(Rc::strong_count(&name), 2);
+///
+/// let future = clone!(name => async move { name.len() });
+/// assert_eq!(Rc::strong_count(&name), 3);
+/// drop(future);
+/// ```
+#[macro_export]
+macro_rules! clone {
+    ($($name:ident),+ $(,)? => $body:expr) => {{
+        $(let $name = ::core::clone::Clone::clone(&$name);)+
+        $body
+    }};
+}
+
+/// Creates a closure or an async block capturing each variable as specified.
+///
+/// Each variable is captured by `ref`, `ref mut`, `clone` or `move`. The
+/// closure or the async block becomes `move`, so the other variables are moved.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::capture,
+///     std::rc::Rc,
+/// };
+///
+/// let prefix = String::from("> ");
+/// let shared = Rc::new(1);
+/// let mut count = 0;
+/// let owned = vec![1, 2];
+///
+/// let mut closure = capture!([ref prefix, clone shared, ref mut count, move owned] || {
+///     *count += 1;
+///     format!("{prefix}{} {}", shared, owned.len())
+/// });
+///
+/// assert_eq!(closure(), "> 1 2");
+/// drop(closure);
+/// assert_eq!(count, 1);
+/// assert_eq!(prefix, "> ");
+/// assert_eq!(Rc::strong_count(&shared), 1);
+///
+/// let future = capture!([clone shared] async { *shared + 1 });
+/// assert_eq!(Rc::strong_count(&shared), 2);
+/// drop(future);
+/// ```
+#[macro_export]
+macro_rules! capture {
+    (@munch [$($let:tt)*] [ref mut $name:ident $(, $($rest:tt)*)?] $($body:tt)*) => {
+        $crate::capture!(@munch [$($let)* let $name = &mut $name;] [$($($rest)*)?] $($body)*)
+    };
+    (@munch [$($let:tt)*] [ref $name:ident $(, $($rest:tt)*)?] $($body:tt)*) => {
+        $crate::capture!(@munch [$($let)* let $name = &$name;] [$($($rest)*)?] $($body)*)
+    };
+    (@munch [$($let:tt)*] [clone $name:ident $(, $($rest:tt)*)?] $($body:tt)*) => {
+        $crate::capture!(
+            @munch
+            [$($let)* let $name = ::core::clone::Clone::clone(&$name);]
+            [$($($rest)*)?]
+            $($body)*
+        )
+    };
+    (@munch [$($let:tt)*] [move $name:ident $(, $($rest:tt)*)?] $($body:tt)*) => {
+        $crate::capture!(@munch [$($let)*] [$($($rest)*)?] $($body)*)
+    };
+    (@munch [$($let:tt)*] [] async $(move)? $($body:tt)*) => {{
+        $($let)*
+        async move $($body)*
+    }};
+    (@munch [$($let:tt)*] [] $(move)? $($body:tt)*) => {{
+        $($let)*
+        move $($body)*
+    }};
+    ([$($capture:tt)*] $($body:tt)*) => {
+        $crate::capture!(@munch [] [$($capture)*] $($body)*)
+    };
+}
+
+/// Threads a value through a sequence of calls.
+///
+/// Each stage is a callable or a call with `_` marking where the value goes.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::pipe;
+///
+/// fn double(value: i32) -> i32 {
+///     value * 2
+/// }
+///
+/// fn add(left: i32, right: i32) -> i32 {
+///     left + right
+/// }
+///
+/// let value = pipe!(3 => double => add(1, _) => |value| value * 10 => i64::from);
+/// assert_eq!(value, 70);
+/// ```
+#[macro_export]
+macro_rules! pipe {
+    (@args $value:ident [$($stage:tt)*] [$($callee:tt)*] [$($done:tt)*] [$($found:tt)?] [_ $($argument:tt)*] [$($rest:tt)*]) => {
+        $crate::pipe!(@args $value [$($stage)*] [$($callee)*] [$($done)* $value] [_] [$($argument)*] [$($rest)*])
+    };
+    (@args $value:ident [$($stage:tt)*] [$($callee:tt)*] [$($done:tt)*] [$($found:tt)?] [$token:tt $($argument:tt)*] [$($rest:tt)*]) => {
+        $crate::pipe!(@args $value [$($stage)*] [$($callee)*] [$($done)* $token] [$($found)?] [$($argument)*] [$($rest)*])
+    };
+    (@args $value:ident [$($stage:tt)*] [$($callee:tt)*] [$($done:tt)*] [_] [] [$($rest:tt)*]) => {
+        $crate::pipe!(@munch $value [$($stage)* let $value = $($callee)*($($done)*);] [] $($rest)*)
+    };
+    (@args $value:ident [$($stage:tt)*] [$($callee:tt)*] [$($done:tt)*] [] [] [$($rest:tt)*]) => {
+        $crate::pipe!(@munch $value [$($stage)* let $value = ($($callee)*($($done)*))($value);] [] $($rest)*)
+    };
+    (@munch $value:ident [$($stage:tt)*] [$($callee:tt)+] ($($argument:tt)*) $(=> $($rest:tt)+)?) => {
+        $crate::pipe!(@args $value [$($stage)*] [$($callee)+] [] [] [$($argument)*] [$($($rest)+)?])
+    };
+    (@munch $value:ident [$($stage:tt)*] [$($callee:tt)+] $(=> $($rest:tt)+)?) => {
+        $crate::pipe!(@munch $value [$($stage)* let $value = ($($callee)+)($value);] [] $($($rest)+)?)
+    };
+    (@munch $value:ident [$($stage:tt)*] []) => {{
+        $($stage)*
+        $value
+    }};
+    (@munch $value:ident [$($stage:tt)*] [$($callee:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::pipe!(@munch $value [$($stage)*] [$($callee)* $token] $($rest)*)
+    };
+    ($value:expr $(=> $($rest:tt)+)?) => {
+        $crate::pipe!(@munch value [let value = $value;] [] $($($rest)+)?)
+    };
+}
+
+/// Inspects a value and returns it.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::{
+///     pipe,
+///     tap,
+/// };
+///
+/// let mut seen = Vec::new();
+///
+/// let value = pipe!(
+///     tap!(2, |value| seen.push(*value)) => |value| value * 3
+/// );
+///
+/// assert_eq!(tap!(value, |value| assert_eq!(*value, 6)), 6);
+/// assert_eq!(seen, [2]);
+/// ```
+#[macro_export]
+macro_rules! tap {
+    ($value:expr, $inspect:expr $(,)?) => {
+        $crate::__private::tap($value, $inspect)
+    };
+}
+
+/// Calls methods on a value in sequence and returns it.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::cascade;
+///
+/// let values = cascade!(Vec::new();
+///     .push(1);
+///     .push(2);
+///     .extend([3, 4]);
+/// );
+///
+/// assert_eq!(values, [1, 2, 3, 4]);
+/// ```
+#[macro_export]
+macro_rules! cascade {
+    ($receiver:expr; $(.$method:ident($($argument:expr),* $(,)?));* $(;)?) => {{
+        #[allow(unused_mut)]
+        let mut receiver = $receiver;
+        $(receiver.$method($($argument),*);)*
+        receiver
+    }};
+}
+
+/// Composes functions from left to right into a closure.
+///
+/// The functions are called in the closure, so generic functions are inferred
+/// at each call.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::compose;
+///
+/// fn double(value: i32) -> i32 {
+///     value * 2
+/// }
+///
+/// let length = compose!(str::trim, str::len);
+/// assert_eq!(length("  abc "), 3);
+///
+/// let calculate = compose!(i32::abs, double, |value| value + 1);
+/// assert_eq!(calculate(-3), 7);
+/// ```
+#[macro_export]
+macro_rules! compose {
+    (@apply [$($value:tt)*]) => {
+        $($value)*
+    };
+    (@apply [$($value:tt)*] $function:expr $(, $rest:expr)*) => {
+        $crate::compose!(@apply [($function)($($value)*)] $($rest),*)
+    };
+    ($($function:expr),+ $(,)?) => {
+        move |value| $crate::compose!(@apply [value] $($function),+)
+    };
+}
+
+/// Binds a value to a name for a block.
+///
+/// The value is dropped at the end of the block, so the block cannot return
+/// anything borrowing it.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::with,
+///     std::cell::RefCell,
+/// };
+///
+/// let log = RefCell::new(Vec::new());
+///
+/// let len = with!(RefCell::borrow_mut(&log) as mut entries {
+///     entries.push("opened");
+///     entries.len()
+/// });
+///
+/// assert_eq!(len, 1);
+///
+/// // The borrow has been released.
+/// RefCell::borrow_mut(&log).push("closed");
+/// ```
+#[macro_export]
+macro_rules! with {
+    (@munch [$($value:tt)*] as mut $name:ident { $($body:tt)* }) => {{
+        let mut $name = $($value)*;
+        $($body)*
+    }};
+    (@munch [$($value:tt)*] as $name:ident { $($body:tt)* }) => {{
+        let $name = $($value)*;
+        $($body)*
+    }};
+    (@munch [$($value:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::with!(@munch [$($value)* $token] $($rest)*)
+    };
+    ($($token:tt)*) => {
+        $crate::with!(@munch [] $($token)*)
+    };
+}
+
+/// A block which can be left early with a value.
+///
+/// This works without label-break-value by wrapping the block in a `loop`, so
+/// `continue` without a label restarts the block. Use [`leave!`] or `break` to
+/// leave it.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::{
+///     block,
+///     leave,
+/// };
+///
+/// fn parse(text: &str) -> u32 {
+///     block!('parse: {
+///         if text.is_empty() {
+///             leave!('parse, 0);
+///         }
+///
+///         text.len() as u32
+///     })
+/// }
+///
+/// assert_eq!(parse(""), 0);
+/// assert_eq!(parse("abc"), 3);
+///
+/// let value = block!({
+///     leave!(1);
+/// });
+///
+/// assert_eq!(value, 1);
+/// ```
+#[macro_export]
+macro_rules! block {
+    ($label:lifetime: { $($body:tt)* }) => {{
+        #[allow(unreachable_code, clippy::diverging_sub_expression, clippy::never_loop)]
+        let value = $label: loop {
+            break $label ({ $($body)* });
+        };
+
+        value
+    }};
+    ({ $($body:tt)* }) => {{
+        #[allow(unreachable_code, clippy::diverging_sub_expression, clippy::never_loop)]
+        let value = loop {
+            break ({ $($body)* });
+        };
+
+        value
+    }};
+}
+
+/// Leaves a [`block!`] with a value.
+///
+/// Without a label, the innermost [`block!`] or loop is left.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::{
+///     block,
+///     leave,
+/// };
+///
+/// let value = block!('outer: {
+///     for i in 0.. {
+///         if i == 3 {
+///             leave!('outer, i * 10);
+///         }
+///     }
+///
+///     0
+/// });
+///
+/// assert_eq!(value, 30);
+/// ```
+#[macro_export]
+macro_rules! leave {
+    () => {
+        break
+    };
+    ($label:lifetime $(, $value:expr)? $(,)?) => {
+        break $label $($value)?
+    };
+    ($value:expr $(,)?) => {
+        break $value
+    };
+}
+
+/// Returns early if a condition holds.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::return_if;
+///
+/// fn check(value: i32) -> &'static str {
+///     return_if!(value < 0, "negative");
+///     return_if!(value == 0, "zero");
+///     "positive"
+/// }
+///
+/// assert_eq!(check(-1), "negative");
+/// assert_eq!(check(0), "zero");
+/// assert_eq!(check(1), "positive");
+///
+/// fn touch(touched: &mut bool, skip: bool) {
+///     return_if!(skip);
+///     *touched = true;
+/// }
+///
+/// let mut touched = false;
+/// touch(&mut touched, true);
+/// assert!(!touched);
+/// ```
+#[macro_export]
+macro_rules! return_if {
+    ($condition:expr $(, $value:expr)? $(,)?) => {
+        if $condition {
+            return $($value)?;
+        }
+    };
+}
+
+/// Returns early if a pattern matches.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::return_if_let;
+///
+/// fn describe(value: Result<i32, &'static str>) -> String {
+///     return_if_let!(Err(error) = value => format!("error: {error}"));
+///     String::from("ok")
+/// }
+///
+/// assert_eq!(describe(Err("failed")), "error: failed");
+/// assert_eq!(describe(Ok(1)), "ok");
+/// ```
+#[macro_export]
+macro_rules! return_if_let {
+    ($pattern:pat = $value:expr $(=> $result:expr)? $(,)?) => {
+        if let $pattern = $value {
+            return $($result)?;
+        }
+    };
+}
+
+/// Breaks a loop if a condition holds.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::break_if;
+///
+/// let mut count = 0;
+///
+/// loop {
+///     count += 1;
+///     break_if!(count == 3);
+/// }
+///
+/// assert_eq!(count, 3);
+///
+/// let found = 'outer: loop {
+///     for i in 0.. {
+///         break_if!('outer, i * i > 50, i);
+///     }
+/// };
+///
+/// assert_eq!(found, 8);
+/// ```
+#[macro_export]
+macro_rules! break_if {
+    ($label:lifetime, $condition:expr $(, $value:expr)? $(,)?) => {
+        if $condition {
+            break $label $($value)?;
+        }
+    };
+    ($condition:expr $(, $value:expr)? $(,)?) => {
+        if $condition {
+            break $($value)?;
+        }
+    };
+}
+
+/// Continues a loop if a condition holds.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::continue_if;
+///
+/// let mut odds = Vec::new();
+///
+/// for i in 0..6 {
+///     continue_if!(i % 2 == 0);
+///     odds.push(i);
+/// }
+///
+/// assert_eq!(odds, [1, 3, 5]);
+///
+/// let mut pairs = Vec::new();
+///
+/// 'outer: for i in 0..3 {
+///     for j in 0..3 {
+///         continue_if!('outer, j > i);
+///         pairs.push((i, j));
+///     }
+/// }
+///
+/// assert_eq!(pairs, [(0, 0), (1, 0), (1, 1), (2, 0), (2, 1), (2, 2)]);
+/// ```
+#[macro_export]
+macro_rules! continue_if {
+    ($label:lifetime, $condition:expr $(,)?) => {
+        if $condition {
+            continue $label;
+        }
+    };
+    ($condition:expr $(,)?) => {
+        if $condition {
+            continue;
+        }
+    };
+}
+
+/// An `if` with the condition inverted.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::unless;
+///
+/// let values = vec![1, 2, 3];
+/// let mut checked = false;
+///
+/// unless!(values.is_empty() {
+///     checked = true;
+/// });
+///
+/// assert!(checked);
+///
+/// let text = unless!(values.contains(&4) {
+///     "missing"
+/// } else {
+///     "found"
+/// });
+///
+/// assert_eq!(text, "missing");
+/// ```
+#[macro_export]
+macro_rules! unless {
+    (@munch [$($condition:tt)+] { $($then:tt)* }) => {
+        if !($($condition)+) {
+            $($then)*
+        }
+    };
+    (@munch [$($condition:tt)+] { $($then:tt)* } else { $($otherwise:tt)* }) => {
+        if !($($condition)+) {
+            $($then)*
+        } else {
+            $($otherwise)*
+        }
+    };
+    (@munch [$($condition:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::unless!(@munch [$($condition)* $token] $($rest)*)
+    };
+    ($($token:tt)*) => {
+        $crate::unless!(@munch [] $($token)*)
+    };
+}
+
+/// A loop evaluating its body before checking the condition.
+///
+/// The condition can use the bindings of the body. `continue` in the body
+/// skips the condition.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::do_while;
+///
+/// let mut values = vec![3, 2, 1];
+/// let mut popped = Vec::new();
+///
+/// do_while!({
+///     let value = values.pop();
+///     popped.push(value);
+/// } while value.is_some());
+///
+/// assert_eq!(popped, [Some(1), Some(2), Some(3), None]);
+///
+/// let mut count = 0;
+///
+/// do_while!({
+///     count += 1;
+/// } while false);
+///
+/// assert_eq!(count, 1);
+/// ```
+#[macro_export]
+macro_rules! do_while {
+    ({ $($body:tt)* } while $condition:expr $(,)?) => {
+        loop {
+            $($body)*
+
+            if !($condition) {
+                break;
+            }
+        }
+    };
+}
+
+/// A `while` with the condition inverted.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::until;
+///
+/// let mut value = 1;
+///
+/// until!(value > 100 {
+///     value *= 2;
+/// });
+///
+/// assert_eq!(value, 128);
+/// ```
+#[macro_export]
+macro_rules! until {
+    (@munch [$($condition:tt)+] { $($body:tt)* }) => {
+        while !($($condition)+) {
+            $($body)*
+        }
+    };
+    (@munch [$($condition:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::until!(@munch [$($condition)* $token] $($rest)*)
+    };
+    ($($token:tt)*) => {
+        $crate::until!(@munch [] $($token)*)
+    };
+}
+
+/// Evaluates a block a number of times.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::repeat_n;
+///
+/// let mut text = String::new();
+///
+/// repeat_n!(3 {
+///     text.push('a');
+/// });
+///
+/// assert_eq!(text, "aaa");
+///
+/// let mut indices = Vec::new();
+///
+/// repeat_n!(text.len(), i {
+///     indices.push(i);
+/// });
+///
+/// assert_eq!(indices, [0, 1, 2]);
+/// ```
+#[macro_export]
+macro_rules! repeat_n {
+    (@munch [$($count:tt)+] , $index:ident { $($body:tt)* }) => {
+        for $index in 0..$($count)+ {
+            $($body)*
+        }
+    };
+    (@munch [$($count:tt)+] { $($body:tt)* }) => {
+        for _ in 0..$($count)+ {
+            $($body)*
+        }
+    };
+    (@munch [$($count:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::repeat_n!(@munch [$($count)* $token] $($rest)*)
+    };
+    ($($token:tt)*) => {
+        $crate::repeat_n!(@munch [] $($token)*)
+    };
+}
+
+/// A `for` loop with an `else` block evaluated if the loop is not broken.
+///
+/// The loop evaluates to the value of `break` or the `else` block.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::for_else;
+///
+/// fn find(values: &[i32], target: i32) -> Option<usize> {
+///     for_else!(for (index, value) in values.iter().enumerate() {
+///         if *value == target {
+///             break Some(index);
+///         }
+///     } else {
+///         None
+///     })
+/// }
+///
+/// assert_eq!(find(&[1, 2, 3], 2), Some(1));
+/// assert_eq!(find(&[1, 2, 3], 4), None);
+///
+/// let mut missing = false;
+///
+/// for_else!(for value in [1, 3, 5] {
+///     if value % 2 == 0 {
+///         break;
+///     }
+/// } else {
+///     missing = true;
+/// });
+///
+/// assert!(missing);
+/// ```
+#[macro_export]
+macro_rules! for_else {
+    (@munch $pattern:pat, [$($iterator:tt)+] { $($body:tt)* } else { $($otherwise:tt)* }) => {{
+        let mut iterator = ::core::iter::IntoIterator::into_iter($($iterator)+);
+
+        loop {
+            match ::core::iter::Iterator::next(&mut iterator) {
+                ::core::option::Option::Some($pattern) => {
+                    $($body)*
+                },
+                ::core::option::Option::None => break { $($otherwise)* },
+            }
+        }
+    }};
+    (@munch $pattern:pat, [$($iterator:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::for_else!(@munch $pattern, [$($iterator)* $token] $($rest)*)
+    };
+    (for $pattern:pat in $($rest:tt)*) => {
+        $crate::for_else!(@munch $pattern, [] $($rest)*)
+    };
+}
+
+/// Evaluates a fallible expression until it succeeds or runs out of attempts.
+///
+/// It evaluates to the first `Ok` or the last `Err`. `on_retry` is called with
+/// the number of the failed attempt and the error before each retry.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::retry;
+///
+/// let mut calls = 0;
+///
+/// let result: Result<i32, String> = retry!(3, {
+///     calls += 1;
+///
+///     if calls < 3 {
+///         Err(format!("attempt {calls} failed"))
+///     } else {
+///         Ok(calls)
+///     }
+/// });
+///
+/// assert_eq!(result, Ok(3));
+///
+/// let mut retries = Vec::new();
+///
+/// let result: Result<(), &str> = retry!(
+///     2,
+///     Err("failed"),
+///     on_retry = |attempt, error| retries.push((attempt, *error))
+/// );
+///
+/// assert_eq!(result, Err("failed"));
+/// assert_eq!(retries, [(1, "failed")]);
+/// ```
+#[macro_export]
+macro_rules! retry {
+    ($attempts:expr, $body:expr $(, on_retry = $on_retry:expr)? $(,)?) => {{
+        let attempts: usize = $attempts;
+        let mut attempt = 1;
+
+        loop {
+            match $body {
+                ::core::result::Result::Ok(value) => break ::core::result::Result::Ok(value),
+                ::core::result::Result::Err(error) if attempt >= attempts => {
+                    break ::core::result::Result::Err(error)
+                },
+                ::core::result::Result::Err(error) => {
+                    $($crate::__private::on_retry(attempt, &error, $on_retry);)?
+                    attempt += 1;
+                },
+            }
+        }
+    }};
+}
+
+/// Measures how long a block takes.
+///
+/// The elapsed time is reported even if the block exits early. It is printed to
+/// the standard error by default, or passed to a sink with the label.
+///
+/// This requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::timeit,
+///     std::time::Duration,
+/// };
+///
+/// let value = timeit!("sum", { (1..=10).sum::<i32>() });
+///
+/// assert_eq!(value, 55);
+///
+/// let mut reports = Vec::new();
+///
+/// timeit!(
+///     "sleep",
+///     {
+///         std::thread::sleep(Duration::from_millis(1));
+///     },
+///     |label, elapsed| reports.push((label, elapsed))
+/// );
+///
+/// assert_eq!(reports[0].0, "sleep");
+/// assert!(reports[0].1 >= Duration::from_millis(1));
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! timeit {
+    ($label:expr, $body:block $(,)?) => {
+        $crate::timeit!($label, $body, $crate::__private::report_elapsed)
+    };
+    ($label:expr, $body:block, $sink:expr $(,)?) => {{
+        let label = $label;
+        let start = $crate::__private::Instant::now();
+
+        $crate::defer! {
+            $crate::__private::sink_elapsed(label, start.elapsed(), $sink);
+        }
+
+        $body
+    }};
+}
+
+/// Evaluates a block at most once per program execution.
+///
+/// It evaluates to `Some` with the value of the block the first time and `None`
+/// afterwards. Concurrent callers do not wait for the first evaluation.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::once;
+///
+/// let mut count = 0;
+///
+/// for _ in 0..3 {
+///     once! {
+///         count += 1;
+///     };
+/// }
+///
+/// assert_eq!(count, 1);
+///
+/// let mut first = || once! { "first" };
+/// assert_eq!(first(), Some("first"));
+/// assert_eq!(first(), None);
+/// ```
+#[macro_export]
+macro_rules! once {
+    ($($token:tt)*) => {{
+        static DONE: ::core::sync::atomic::AtomicBool = ::core::sync::atomic::AtomicBool::new(false);
+
+        if ::core::sync::atomic::AtomicBool::swap(&DONE, true, ::core::sync::atomic::Ordering::AcqRel) {
+            ::core::option::Option::None
+        } else {
+            ::core::option::Option::Some({ $($token)* })
+        }
+    }};
+}
+
+/// A value initialized on the first access, usable in statics.
+///
+/// Concurrent accesses spin until the initialization finishes. If the
+/// initialization panics, the instance is poisoned and later accesses panic.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::Lazy;
+///
+/// static VALUE: Lazy<Vec<i32>> = Lazy::new(|| vec![1, 2, 3]);
+///
+/// assert_eq!(VALUE.len(), 3);
+/// assert_eq!(Lazy::force(&VALUE), &[1, 2, 3]);
+/// ```
+pub struct Lazy<T, F = fn() -> T> {
+    state: AtomicU8,
+    cell: UnsafeCell<LazyCell<T, F>>,
+}
+
+enum LazyCell<T, F> {
+    Uninit(F),
+    Init(T),
+    Poisoned,
+}
+
+const LAZY_INCOMPLETE: u8 = 0;
+const LAZY_RUNNING: u8 = 1;
+const LAZY_COMPLETE: u8 = 2;
+const LAZY_POISONED: u8 = 3;
+
+impl<T, F: FnOnce() -> T> Lazy<T, F> {
+    /// Creates a new instance.
+    pub const fn new(init: F) -> Self {
+        Self {
+            state: AtomicU8::new(LAZY_INCOMPLETE),
+            cell: UnsafeCell::new(LazyCell::Uninit(init)),
+        }
+    }
+
+    /// Initializes the value if needed and returns a reference to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the instance is poisoned.
+    pub fn force(lazy: &Self) -> &T {
+        loop {
+            match AtomicU8::compare_exchange_weak(
+                &lazy.state,
+                LAZY_INCOMPLETE,
+                LAZY_RUNNING,
+                Ordering::Acquire,
+                Ordering::Acquire,
+            ) {
+                Result::Ok(_) => {
+                    let poison = Defer::new(|| {
+                        AtomicU8::store(&lazy.state, LAZY_POISONED, Ordering::Release)
+                    });
+
+                    // SAFETY: The running state gives this thread exclusive access to the cell.
+                    let cell = unsafe { &mut *UnsafeCell::get(&lazy.cell) };
+
+                    let LazyCell::Uninit(init) = core::mem::replace(cell, LazyCell::Poisoned)
+                    else {
+                        unreachable!()
+                    };
+
+                    *cell = LazyCell::Init(init());
+                    Defer::cancel(poison);
+                    AtomicU8::store(&lazy.state, LAZY_COMPLETE, Ordering::Release);
+                },
+                Result::Err(LAZY_COMPLETE) => {
+                    // SAFETY: The complete state guarantees that the cell is never written again.
+                    let LazyCell::Init(value) = (unsafe { &*UnsafeCell::get(&lazy.cell) }) else {
+                        unreachable!()
+                    };
+
+                    return value;
+                },
+                Result::Err(LAZY_POISONED) => panic!("Lazy instance has previously been poisoned"),
+                Result::Err(_) => core::hint::spin_loop(),
+            }
+        }
+    }
+}
+
+impl<T, F: FnOnce() -> T> Deref for Lazy<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        Self::force(self)
+    }
+}
+
+// SAFETY: The cell is written only by the thread in the running state.
+unsafe impl<T: Send + Sync, F: Send> Sync for Lazy<T, F> {}
+
+/// Declares lazily initialized statics.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::lazy,
+///     std::sync::atomic::{
+///         AtomicUsize,
+///         Ordering,
+///     },
+/// };
+///
+/// static CALLS: AtomicUsize = AtomicUsize::new(0);
+///
+/// lazy! {
+///     static NAME: String = {
+///         CALLS.fetch_add(1, Ordering::Relaxed);
+///         String::from("keymacro")
+///     };
+///
+///     pub(crate) static LENGTH: usize = NAME.len();
+/// }
+///
+/// assert_eq!(CALLS.load(Ordering::Relaxed), 0);
+/// assert_eq!(*LENGTH, 8);
+/// assert_eq!(*NAME, "keymacro");
+/// assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+/// ```
+#[macro_export]
+macro_rules! lazy {
+    ($($(#[$attribute:meta])* $visibility:vis static $name:ident: $type:ty = $value:expr;)*) => {
+        $(
+            $(#[$attribute])*
+            $visibility static $name: $crate::Lazy<$type> = $crate::Lazy::new(|| $value);
+        )*
+    };
+}
+
+/// A global value guarded by a lock.
+///
+/// It uses [`std::sync::Mutex`] with the `std` feature and a spin lock
+/// otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::Global;
+///
+/// static NAMES: Global<Vec<&str>> = Global::new(Vec::new());
+///
+/// NAMES.with(|names| names.push("a"));
+/// NAMES.with(|names| names.push("b"));
+/// assert_eq!(NAMES.get(), ["a", "b"]);
+/// assert_eq!(NAMES.replace(Vec::new()), ["a", "b"]);
+/// NAMES.set(vec!["c"]);
+/// assert_eq!(NAMES.get(), ["c"]);
+/// ```
+pub struct Global<T> {
+    #[cfg(feature = "std")]
+    value: std::sync::Mutex<T>,
+    #[cfg(not(feature = "std"))]
+    locked: AtomicBool,
+    #[cfg(not(feature = "std"))]
+    value: UnsafeCell<T>,
+}
+
+impl<T> Global<T> {
+    /// Creates a new instance.
+    pub const fn new(value: T) -> Self {
+        Self {
+            #[cfg(feature = "std")]
+            value: std::sync::Mutex::new(value),
+            #[cfg(not(feature = "std"))]
+            locked: AtomicBool::new(false),
+            #[cfg(not(feature = "std"))]
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Locks the value and passes it to the closure.
+    #[cfg(feature = "std")]
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut value = match std::sync::Mutex::lock(&self.value) {
+            Result::Ok(value) => value,
+            Result::Err(error) => std::sync::PoisonError::into_inner(error),
+        };
+
+        f(&mut value)
+    }
+
+    /// Locks the value and passes it to the closure.
+    #[cfg(not(feature = "std"))]
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        while AtomicBool::compare_exchange_weak(
+            &self.locked,
+            false,
+            true,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        )
+        .is_err()
+        {
+            core::hint::spin_loop();
+        }
+
+        let _unlock = Defer::new(|| AtomicBool::store(&self.locked, false, Ordering::Release));
+
+        // SAFETY: The lock gives this thread exclusive access to the value.
+        f(unsafe { &mut *UnsafeCell::get(&self.value) })
+    }
+
+    /// Returns a clone of the value.
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.with(|value| T::clone(value))
+    }
+
+    /// Sets the value.
+    pub fn set(&self, value: T) {
+        self.replace(value);
+    }
+
+    /// Sets the value and returns the previous one.
+    pub fn replace(&self, value: T) -> T {
+        self.with(|current| core::mem::replace(current, value))
+    }
+}
+
+// SAFETY: The value is accessed only while the lock is held.
+#[cfg(not(feature = "std"))]
+unsafe impl<T: Send> Sync for Global<T> {}
+
+/// Declares global statics.
+///
+/// A static declared with `atomic` and a primitive type uses the matching
+/// atomic type. Any other static uses [`Global`], whose methods lock it.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::global,
+///     std::sync::atomic::Ordering,
+/// };
+///
+/// global! {
+///     static COUNTER: atomic usize = 0;
+///     static READY: atomic bool = false;
+///
+///     /// The registered names.
+///     pub static NAMES: Vec<&'static str> = Vec::new();
+/// }
+///
+/// COUNTER.fetch_add(2, Ordering::Relaxed);
+/// READY.store(true, Ordering::Relaxed);
+/// NAMES.with(|names| names.push("a"));
+///
+/// assert_eq!(COUNTER.load(Ordering::Relaxed), 2);
+/// assert!(READY.load(Ordering::Relaxed));
+/// assert_eq!(NAMES.get(), ["a"]);
+/// ```
+#[macro_export]
+macro_rules! global {
+    (@munch) => {};
+    (
+        @munch
+        $(#[$attribute:meta])*
+        $visibility:vis static $name:ident: atomic $type:ident = $value:expr;
+        $($rest:tt)*
+    ) => {
+        $(#[$attribute])*
+        $visibility static $name: $crate::global!(@atomic $type) = <$crate::global!(@atomic $type)>::new($value);
+
+        $crate::global!(@munch $($rest)*);
+    };
+    (
+        @munch
+        $(#[$attribute:meta])*
+        $visibility:vis static $name:ident: $type:ty = $value:expr;
+        $($rest:tt)*
+    ) => {
+        $(#[$attribute])*
+        $visibility static $name: $crate::Global<$type> = $crate::Global::new($value);
+
+        $crate::global!(@munch $($rest)*);
+    };
+    (@atomic bool) => { ::core::sync::atomic::AtomicBool };
+    (@atomic i8) => { ::core::sync::atomic::AtomicI8 };
+    (@atomic i16) => { ::core::sync::atomic::AtomicI16 };
+    (@atomic i32) => { ::core::sync::atomic::AtomicI32 };
+    (@atomic i64) => { ::core::sync::atomic::AtomicI64 };
+    (@atomic isize) => { ::core::sync::atomic::AtomicIsize };
+    (@atomic u8) => { ::core::sync::atomic::AtomicU8 };
+    (@atomic u16) => { ::core::sync::atomic::AtomicU16 };
+    (@atomic u32) => { ::core::sync::atomic::AtomicU32 };
+    (@atomic u64) => { ::core::sync::atomic::AtomicU64 };
+    (@atomic usize) => { ::core::sync::atomic::AtomicUsize };
+    (@atomic $type:ident) => {
+        ::core::compile_error!(::core::concat!("`", ::core::stringify!($type), "` has no atomic type"))
+    };
+    ($($token:tt)*) => {
+        $crate::global!(@munch $($token)*);
+    };
+}
+
+/// A handle to a thread local value.
+///
+/// This requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::ThreadStatic,
+///     std::cell::RefCell,
+/// };
+///
+/// thread_local! {
+///     static KEY: RefCell<i32> = const { RefCell::new(1) };
+/// }
+///
+/// static VALUE: ThreadStatic<i32> = ThreadStatic::new(&KEY);
+///
+/// VALUE.set(2);
+/// VALUE.with_mut(|value| *value += 1);
+/// assert_eq!(VALUE.get(), 3);
+/// ```
+#[cfg(feature = "std")]
+pub struct ThreadStatic<T: 'static> {
+    key: &'static std::thread::LocalKey<core::cell::RefCell<T>>,
+}
+
+#[cfg(feature = "std")]
+impl<T: 'static> ThreadStatic<T> {
+    /// Creates a new instance.
+    pub const fn new(key: &'static std::thread::LocalKey<core::cell::RefCell<T>>) -> Self {
+        Self {
+            key,
+        }
+    }
+
+    /// Passes a reference to the value of the current thread to the closure.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        self.key
+            .with(|value| f(&core::cell::RefCell::borrow(value)))
+    }
+
+    /// Passes a mutable reference to the value of the current thread to the closure.
+    pub fn with_mut<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        self.key
+            .with(|value| f(&mut core::cell::RefCell::borrow_mut(value)))
+    }
+
+    /// Returns the value of the current thread.
+    pub fn get(&self) -> T
+    where
+        T: Copy,
+    {
+        self.with(|value| *value)
+    }
+
+    /// Sets the value of the current thread.
+    pub fn set(&self, value: T) {
+        self.replace(value);
+    }
+
+    /// Sets the value of the current thread and returns the previous one.
+    pub fn replace(&self, value: T) -> T {
+        self.with_mut(|current| core::mem::replace(current, value))
+    }
+}
+
+/// Declares thread local statics as [`ThreadStatic`].
+///
+/// This requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::thread_static;
+///
+/// thread_static! {
+///     static COUNT: u32 = 0;
+///
+///     /// The names of the current thread.
+///     pub static NAMES: Vec<&'static str> = Vec::new();
+/// }
+///
+/// COUNT.set(1);
+/// NAMES.with_mut(|names| names.push("main"));
+///
+/// std::thread::spawn(|| {
+///     assert_eq!(COUNT.get(), 0);
+///     NAMES.with(|names| assert!(names.is_empty()));
+/// })
+/// .join()
+/// .unwrap();
+///
+/// assert_eq!(COUNT.get(), 1);
+/// NAMES.with(|names| assert_eq!(names, &["main"]));
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! thread_static {
+    ($($(#[$attribute:meta])* $visibility:vis static $name:ident: $type:ty = $value:expr;)*) => {
+        $(
+            $(#[$attribute])*
+            $visibility static $name: $crate::ThreadStatic<$type> = {
+                ::std::thread_local! {
+                    static KEY: ::core::cell::RefCell<$type> = ::core::cell::RefCell::new($value);
+                }
+
+                $crate::ThreadStatic::new(&KEY)
+            };
+        )*
+    };
+}
+
+/// Returns early with an error converted by [`From`].
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::bail;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Error(&'static str);
+///
+/// impl From<&'static str> for Error {
+///     fn from(message: &'static str) -> Self {
+///         Self(message)
+///     }
+/// }
+///
+/// fn parse(text: &str) -> Result<i32, Error> {
+///     if text.is_empty() {
+///         bail!("empty");
+///     }
+///
+///     text.parse().map_err(|_| Error("invalid"))
+/// }
+///
+/// assert_eq!(parse("1"), Ok(1));
+/// assert_eq!(parse(""), Err(Error("empty")));
+/// ```
+#[macro_export]
+macro_rules! bail {
+    ($error:expr $(,)?) => {
+        return ::core::result::Result::Err(::core::convert::From::from($error))
+    };
+}
+
+/// Returns early with an error converted by [`From`] unless a condition holds.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::ensure,
+///     std::fmt::Error,
+/// };
+///
+/// fn half(value: i32) -> Result<i32, Error> {
+///     ensure!(value % 2 == 0, Error);
+///     Ok(value / 2)
+/// }
+///
+/// assert_eq!(half(4), Ok(2));
+/// assert_eq!(half(3), Err(Error));
+/// ```
+#[macro_export]
+macro_rules! ensure {
+    ($condition:expr, $error:expr $(,)?) => {
+        if !$condition {
+            $crate::bail!($error);
+        }
+    };
+}
+
+/// Unwraps a [`Result`] like `?`, mapping the error before returning it.
+///
+/// The error is passed to the closure, or wrapped in a [`Contextual`] with the
+/// context string like [`context!`], and then converted by [`From`].
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::{
+///         Contextual,
+///         tri,
+///     },
+///     std::{
+///         error::Error,
+///         num::ParseIntError,
+///     },
+/// };
+///
+/// #[derive(Debug, PartialEq)]
+/// struct ParseError(String);
+///
+/// fn parse(text: &str) -> Result<i32, ParseError> {
+///     Ok(tri!(text.parse::<i32>(), |error| ParseError(
+///         error.to_string()
+///     )))
+/// }
+///
+/// fn parse_with_context(text: &str) -> Result<i32, Contextual<ParseIntError>> {
+///     Ok(tri!(text.parse::<i32>(), "parsing the value"))
+/// }
+///
+/// fn parse_boxed(text: &str) -> Result<i32, Box<dyn Error>> {
+///     Ok(tri!(text.parse::<i32>(), "parsing the value"))
+/// }
+///
+/// assert_eq!(parse("1"), Ok(1));
+/// assert!(matches!(parse("a"), Err(ParseError(_))));
+/// assert_eq!(
+///     parse_with_context("a").unwrap_err().to_string(),
+///     "parsing the value"
+/// );
+/// assert_eq!(
+///     format!("{:#}", parse_with_context("a").unwrap_err()),
+///     "parsing the value: invalid digit found in string"
+/// );
+/// assert_eq!(
+///     parse_boxed("a").unwrap_err().to_string(),
+///     "parsing the value"
+/// );
+/// ```
+#[macro_export]
+macro_rules! tri {
+    ($result:expr, $context:literal $(,)?) => {
+        match $result {
+            ::core::result::Result::Ok(value) => value,
+            ::core::result::Result::Err(error) => {
+                return ::core::result::Result::Err(::core::convert::From::from(
+                    $crate::Contextual::new($context, error),
+                ));
+            },
+        }
+    };
+    ($result:expr, $map:expr $(,)?) => {
+        match $crate::__private::map_error($result, $map) {
+            ::core::result::Result::Ok(value) => value,
+            ::core::result::Result::Err(error) => {
+                return ::core::result::Result::Err(::core::convert::From::from(error));
+            },
+        }
+    };
+}
+
+/// Unwraps an [`Option`] or returns early.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::ok_or_return;
+///
+/// fn first(values: &[i32]) -> i32 {
+///     let value = ok_or_return!(values.first(), -1);
+///     *value * 2
+/// }
+///
+/// let mut seen = Vec::new();
+///
+/// let mut record = |value: Option<i32>| {
+///     seen.push(ok_or_return!(value));
+/// };
+///
+/// record(Some(1));
+/// record(None);
+///
+/// assert_eq!(first(&[2, 3]), 4);
+/// assert_eq!(first(&[]), -1);
+/// assert_eq!(seen, [1]);
+/// ```
+#[macro_export]
+macro_rules! ok_or_return {
+    ($option:expr $(, $value:expr)? $(,)?) => {
+        match $option {
+            ::core::option::Option::Some(value) => value,
+            ::core::option::Option::None => return $($value)?,
+        }
+    };
+}
+
+/// Unwraps a [`Result`] or returns early.
+///
+/// Without a value, it returns the error converted by [`From`].
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::unwrap_or_return;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Status {
+///     Done(i32),
+///     Failed(String),
+/// }
+///
+/// impl From<std::num::ParseIntError> for Status {
+///     fn from(error: std::num::ParseIntError) -> Self {
+///         Self::Failed(error.to_string())
+///     }
+/// }
+///
+/// fn run(text: &str) -> Status {
+///     Status::Done(unwrap_or_return!(text.parse::<i32>()))
+/// }
+///
+/// fn parse_or_zero(text: &str) -> i32 {
+///     unwrap_or_return!(text.parse::<i32>(), 0) * 2
+/// }
+///
+/// assert_eq!(run("1"), Status::Done(1));
+/// assert!(matches!(run("a"), Status::Failed(_)));
+/// assert_eq!(parse_or_zero("2"), 4);
+/// assert_eq!(parse_or_zero("a"), 0);
+/// ```
+#[macro_export]
+macro_rules! unwrap_or_return {
+    ($result:expr $(,)?) => {
+        match $result {
+            ::core::result::Result::Ok(value) => value,
+            ::core::result::Result::Err(error) => return ::core::convert::From::from(error),
+        }
+    };
+    ($result:expr, $value:expr $(,)?) => {
+        match $result {
+            ::core::result::Result::Ok(value) => value,
+            ::core::result::Result::Err(_) => return $value,
+        }
+    };
+}
+
+/// Binds a refutable pattern or panics.
+///
+/// If the type of the value implements [`Debug`](core::fmt::Debug), the panic
+/// message shows the value.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::let_assert;
+///
+/// #[derive(Debug)]
+/// struct Point {
+///     x: i32,
+///     y: Option<i32>,
+/// }
+///
+/// let_assert!(
+///     Point {
+///         x,
+///         y: Some(y)
+///     } = Point {
+///         x: 1,
+///         y: Some(2)
+///     }
+/// );
+/// assert_eq!((x, y), (1, 2));
+/// ```
+///
+/// ```should_panic
+/// use keymacro::let_assert;
+///
+/// let value: Result<i32, &str> = Err("failed");
+/// let_assert!(Ok(_) = value);
+/// ```
+///
+/// ```should_panic
+/// use keymacro::let_assert;
+///
+/// struct Opaque;
+///
+/// let value: Option<Opaque> = None;
+/// let_assert!(Some(_) = value);
+/// ```
+#[macro_export]
+macro_rules! let_assert {
+    ($pattern:pat = $value:expr $(,)?) => {
+        let value = $value;
+
+        let $pattern = value else {
+            #[allow(unused_imports)]
+            use $crate::__private::{
+                DescribeDebug as _,
+                DescribeOther as _,
+            };
+
+            $crate::__private::let_assert_failed(
+                (&&$crate::__private::Described(&value)).describe(),
+                ::core::stringify!($pattern),
+            )
+        };
+    };
+}
+
+/// Binds a refutable pattern or passes the value to a diverging block.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::guard;
+///
+/// fn parse(value: Result<i32, String>, log: &mut Vec<String>) -> i32 {
+///     guard!(let Ok(value) = value else |value| {
+///         log.push(format!("{value:?}"));
+///         return 0;
+///     });
+///
+///     value * 2
+/// }
+///
+/// let mut log = Vec::new();
+/// assert_eq!(parse(Ok(1), &mut log), 2);
+/// assert_eq!(parse(Err(String::from("failed")), &mut log), 0);
+/// assert_eq!(log, [r#"Err("failed")"#]);
+/// ```
+#[macro_export]
+macro_rules! guard {
+    (@munch [$pattern:pat] [$($value:tt)+] else |$reason:pat_param| $otherwise:block) => {
+        let value = $($value)+;
+
+        let $pattern = value else {
+            let $reason = value;
+            $otherwise
+        };
+    };
+    (@munch [$pattern:pat] [$($value:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::guard!(@munch [$pattern] [$($value)* $token] $($rest)*);
+    };
+    (let $pattern:pat = $($rest:tt)+) => {
+        $crate::guard!(@munch [$pattern] [] $($rest)+);
+    };
+}
+
+/// Returns the value of an expression in [`Option::Some`] if a pattern matches.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::extract;
+///
+/// enum Shape {
+///     Circle(f64),
+///     Rectangle(f64, f64),
+/// }
+///
+/// let circle = Shape::Circle(1.0);
+/// let rectangle = Shape::Rectangle(2.0, 3.0);
+///
+/// assert_eq!(extract!(circle, Shape::Circle(radius) => radius), Some(1.0));
+/// assert_eq!(extract!(rectangle, Shape::Circle(radius) => radius), None);
+/// assert_eq!(
+///     extract!(rectangle, Shape::Rectangle(width, height) if width < height => width * height),
+///     Some(6.0),
+/// );
+/// ```
+#[macro_export]
+macro_rules! extract {
+    ($value:expr, $pattern:pat $(if $guard:expr)? => $result:expr $(,)?) => {
+        match $value {
+            $pattern $(if $guard)? => ::core::option::Option::Some($result),
+            _ => ::core::option::Option::None,
+        }
+    };
+}
+
+/// Asserts that an expression matches a pattern.
+///
+/// The panic message shows the value formatted by [`Debug`](core::fmt::Debug).
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::assert_matches;
+///
+/// let value: Result<i32, &str> = Ok(2);
+///
+/// assert_matches!(value, Ok(_));
+/// assert_matches!(value, Ok(value) if value % 2 == 0);
+/// assert_matches!(value, Ok(1..=3), "the value is {value:?}");
+/// ```
+///
+/// ```should_panic
+/// use keymacro::assert_matches;
+///
+/// let value: Result<i32, &str> = Err("failed");
+/// assert_matches!(value, Ok(_));
+/// ```
+#[macro_export]
+macro_rules! assert_matches {
+    ($value:expr, $pattern:pat $(if $guard:expr)? $(,)?) => {
+        match $value {
+            $pattern $(if $guard)? => {},
+            ref value => $crate::__private::assert_matches_failed(
+                value,
+                ::core::stringify!($pattern $(if $guard)?),
+                ::core::option::Option::None,
+            ),
+        }
+    };
+    ($value:expr, $pattern:pat $(if $guard:expr)?, $($argument:tt)+) => {
+        match $value {
+            $pattern $(if $guard)? => {},
+            ref value => $crate::__private::assert_matches_failed(
+                value,
+                ::core::stringify!($pattern $(if $guard)?),
+                ::core::option::Option::Some(::core::format_args!($($argument)+)),
+            ),
+        }
+    };
+}
+
+/// Returns the default value of a type.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::default;
+///
+/// #[derive(Debug, Default, PartialEq)]
+/// struct Options {
+///     name: String,
+///     count: usize,
+///     verbose: bool,
+/// }
+///
+/// let options = Options {
+///     count: 1,
+///     ..default!()
+/// };
+///
+/// assert_eq!(options.name, "");
+/// assert_eq!(default!(Options), Options::default());
+/// assert_eq!(default!(Vec<i32>), []);
+/// ```
+#[macro_export]
+macro_rules! default {
+    () => {
+        ::core::default::Default::default()
+    };
+    ($type:ty $(,)?) => {
+        <$type as ::core::default::Default>::default()
+    };
+}
+
+/// Declares a newtype with opt-in trait implementations.
+///
+/// The options after the declaration are:
+///
+/// - `derive(...)`, which must come first, derives the traits.
+/// - `deref` implements [`Deref`] to the inner type.
+/// - `deref_mut` implements [`DerefMut`] to the inner type.
+/// - `from` implements [`From`] in both directions.
+/// - `as_ref` implements [`AsRef`] and [`AsMut`] for the inner type.
+/// - `ops(...)` forwards the listed operators to the inner type with
+///   [`impl_ops!`].
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::newtype;
+///
+/// newtype! {
+///     /// A length in meters.
+///     pub struct Meters(f64);
+///     derive(Debug, Clone, Copy, PartialEq);
+///     deref;
+///     from;
+///     as_ref;
+///     ops(Add, Sub, AddAssign, Neg);
+/// }
+///
+/// let mut length = Meters::from(1.5) + Meters(2.0);
+/// length += Meters(0.5);
+///
+/// assert_eq!(length, Meters(4.0));
+/// assert_eq!(-length - Meters(1.0), Meters(-5.0));
+/// assert_eq!(length.floor(), 4.0);
+/// assert_eq!(f64::from(length), 4.0);
+/// assert_eq!(*length.as_ref(), 4.0);
+/// ```
+#[macro_export]
+macro_rules! newtype {
+    (@option $name:ident, $inner:ty, deref) => {
+        impl ::core::ops::Deref for $name {
+            type Target = $inner;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+    };
+    (@option $name:ident, $inner:ty, deref_mut) => {
+        impl ::core::ops::DerefMut for $name {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.0
+            }
+        }
+    };
+    (@option $name:ident, $inner:ty, from) => {
+        impl ::core::convert::From<$inner> for $name {
+            fn from(value: $inner) -> Self {
+                Self(value)
+            }
+        }
+
+        impl ::core::convert::From<$name> for $inner {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+    };
+    (@option $name:ident, $inner:ty, as_ref) => {
+        impl ::core::convert::AsRef<$inner> for $name {
+            fn as_ref(&self) -> &$inner {
+                &self.0
+            }
+        }
+
+        impl ::core::convert::AsMut<$inner> for $name {
+            fn as_mut(&mut self) -> &mut $inner {
+                &mut self.0
+            }
+        }
+    };
+    (@option $name:ident, $inner:ty, ops($($operator:ident),* $(,)?)) => {
+        $crate::impl_ops!($name: $($operator),*);
+    };
+    (@option $name:ident, $inner:ty, $option:ident $($arguments:tt)?) => {
+        ::core::compile_error!(::core::concat!("unknown option `", ::core::stringify!($option), "`"));
+    };
+    (
+        $(#[$attribute:meta])*
+        $visibility:vis struct $name:ident($field_visibility:vis $inner:ty);
+        derive($($derive:path),* $(,)?);
+        $($option:ident $(($($argument:tt)*))?;)*
+    ) => {
+        $crate::newtype! {
+            $(#[$attribute])*
+            #[derive($($derive),*)]
+            $visibility struct $name($field_visibility $inner);
+            $($option $(($($argument)*))?;)*
+        }
+    };
+    (
+        $(#[$attribute:meta])*
+        $visibility:vis struct $name:ident($field_visibility:vis $inner:ty);
+        $($option:ident $(($($argument:tt)*))?;)*
+    ) => {
+        $(#[$attribute])*
+        $visibility struct $name($field_visibility $inner);
+
+        $($crate::newtype!(@option $name, $inner, $option $(($($argument)*))?);)*
+    };
+}
+
+/// Declares an enum with [`From`] implementations for its single field tuple
+/// variants.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::enum_from,
+///     std::num::{
+///         ParseFloatError,
+///         ParseIntError,
+///     },
+/// };
+///
+/// enum_from! {
+///     #[derive(Debug)]
+///     pub enum Error {
+///         /// An invalid integer.
+///         Int(ParseIntError),
+///         Float(ParseFloatError),
+///         Range { min: i32, max: i32 },
+///         Empty,
+///     }
+/// }
+///
+/// fn parse(text: &str) -> Result<(i32, f64), Error> {
+///     let (int, float) = text.split_once(' ').ok_or(Error::Empty)?;
+///     Ok((int.parse()?, float.parse()?))
+/// }
+///
+/// assert_eq!(parse("1 2.5").unwrap(), (1, 2.5));
+/// assert!(matches!(parse("a 2.5"), Err(Error::Int(_))));
+/// assert!(matches!(parse("1 b"), Err(Error::Float(_))));
+/// assert!(matches!(parse(""), Err(Error::Empty)));
+/// ```
+#[macro_export]
+macro_rules! enum_from {
+    (@variants $name:ident) => {};
+    (@variants $name:ident $(#[$attribute:meta])* $variant:ident($field:ty) $(, $($rest:tt)*)?) => {
+        impl ::core::convert::From<$field> for $name {
+            fn from(value: $field) -> Self {
+                Self::$variant(value)
+            }
+        }
+
+        $crate::enum_from!(@variants $name $($($rest)*)?);
+    };
+    (
+        @variants $name:ident
+        $(#[$attribute:meta])*
+        $variant:ident $(($($field:ty),* $(,)?))? $({ $($fields:tt)* })? $(= $discriminant:expr)?
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::enum_from!(@variants $name $($($rest)*)?);
+    };
+    ($(#[$attribute:meta])* $visibility:vis enum $name:ident { $($variants:tt)* }) => {
+        $(#[$attribute])*
+        $visibility enum $name {
+            $($variants)*
+        }
+
+        $crate::enum_from!(@variants $name $($variants)*);
+    };
+}
+
+/// Implements operators for a newtype by forwarding them to the inner type.
+///
+/// The supported operators are `Add`, `Sub`, `Mul`, `Div`, `Rem`, `BitAnd`,
+/// `BitOr`, `BitXor`, their `*Assign` variants, `Neg` and `Not`. Each operator
+/// is also implemented for references, which clone the inner values, so the
+/// inner type only needs [`Clone`] and the operator on owned values.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::impl_ops;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// struct Seconds(u64);
+///
+/// impl_ops!(Seconds: Add, Sub, Mul, AddAssign, Not);
+///
+/// let mut total = Seconds(1) + Seconds(2);
+/// total += &Seconds(3);
+///
+/// assert_eq!(total, Seconds(6));
+/// assert_eq!(&total - &Seconds(1), Seconds(5));
+/// assert_eq!(total * &Seconds(2), Seconds(12));
+/// assert_eq!(&total * Seconds(2), Seconds(12));
+/// assert_eq!(!&Seconds(0), Seconds(u64::MAX));
+/// ```
+///
+/// ```
+/// use {
+///     keymacro::impl_ops,
+///     std::time::Duration,
+/// };
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// struct Timeout(Duration);
+///
+/// impl_ops!(Timeout: Add, Sub, AddAssign);
+///
+/// let mut timeout = Timeout(Duration::from_secs(1));
+/// timeout += &Timeout(Duration::from_secs(2));
+///
+/// assert_eq!(&timeout + &timeout, Timeout(Duration::from_secs(6)));
+/// assert_eq!(&timeout - Timeout(Duration::from_secs(1)), Timeout(Duration::from_secs(2)));
+/// ```
+#[macro_export]
+macro_rules! impl_ops {
+    (@operator $name:ident, Add) => { $crate::impl_ops!(@binary $name, Add, add); };
+    (@operator $name:ident, Sub) => { $crate::impl_ops!(@binary $name, Sub, sub); };
+    (@operator $name:ident, Mul) => { $crate::impl_ops!(@binary $name, Mul, mul); };
+    (@operator $name:ident, Div) => { $crate::impl_ops!(@binary $name, Div, div); };
+    (@operator $name:ident, Rem) => { $crate::impl_ops!(@binary $name, Rem, rem); };
+    (@operator $name:ident, BitAnd) => { $crate::impl_ops!(@binary $name, BitAnd, bitand); };
+    (@operator $name:ident, BitOr) => { $crate::impl_ops!(@binary $name, BitOr, bitor); };
+    (@operator $name:ident, BitXor) => { $crate::impl_ops!(@binary $name, BitXor, bitxor); };
+    (@operator $name:ident, AddAssign) => { $crate::impl_ops!(@assign $name, AddAssign, add_assign); };
+    (@operator $name:ident, SubAssign) => { $crate::impl_ops!(@assign $name, SubAssign, sub_assign); };
+    (@operator $name:ident, MulAssign) => { $crate::impl_ops!(@assign $name, MulAssign, mul_assign); };
+    (@operator $name:ident, DivAssign) => { $crate::impl_ops!(@assign $name, DivAssign, div_assign); };
+    (@operator $name:ident, RemAssign) => { $crate::impl_ops!(@assign $name, RemAssign, rem_assign); };
+    (@operator $name:ident, BitAndAssign) => { $crate::impl_ops!(@assign $name, BitAndAssign, bitand_assign); };
+    (@operator $name:ident, BitOrAssign) => { $crate::impl_ops!(@assign $name, BitOrAssign, bitor_assign); };
+    (@operator $name:ident, BitXorAssign) => { $crate::impl_ops!(@assign $name, BitXorAssign, bitxor_assign); };
+    (@operator $name:ident, Neg) => { $crate::impl_ops!(@unary $name, Neg, neg); };
+    (@operator $name:ident, Not) => { $crate::impl_ops!(@unary $name, Not, not); };
+    (@operator $name:ident, $operator:ident) => {
+        ::core::compile_error!(::core::concat!("unsupported operator `", ::core::stringify!($operator), "`"));
+    };
+    (@binary $name:ident, $trait:ident, $method:ident) => {
+        impl ::core::ops::$trait for $name {
+            type Output = $name;
+
+            fn $method(self, rhs: $name) -> Self::Output {
+                $name(::core::ops::$trait::$method(self.0, rhs.0))
+            }
+        }
+
+        impl ::core::ops::$trait<&$name> for $name {
+            type Output = $name;
+
+            fn $method(self, rhs: &$name) -> Self::Output {
+                $name(::core::ops::$trait::$method(self.0, ::core::clone::Clone::clone(&rhs.0)))
+            }
+        }
+
+        impl ::core::ops::$trait<$name> for &$name {
+            type Output = $name;
+
+            fn $method(self, rhs: $name) -> Self::Output {
+                $name(::core::ops::$trait::$method(::core::clone::Clone::clone(&self.0), rhs.0))
+            }
+        }
+
+        impl ::core::ops::$trait<&$name> for &$name {
+            type Output = $name;
+
+            fn $method(self, rhs: &$name) -> Self::Output {
+                $name(::core::ops::$trait::$method(
+                    ::core::clone::Clone::clone(&self.0),
+                    ::core::clone::Clone::clone(&rhs.0),
+                ))
+            }
+        }
+    };
+    (@assign $name:ident, $trait:ident, $method:ident) => {
+        impl ::core::ops::$trait for $name {
+            fn $method(&mut self, rhs: $name) {
+                ::core::ops::$trait::$method(&mut self.0, rhs.0);
+            }
+        }
+
+        impl ::core::ops::$trait<&$name> for $name {
+            fn $method(&mut self, rhs: &$name) {
+                ::core::ops::$trait::$method(&mut self.0, ::core::clone::Clone::clone(&rhs.0));
+            }
+        }
+    };
+    (@unary $name:ident, $trait:ident, $method:ident) => {
+        impl ::core::ops::$trait for $name {
+            type Output = $name;
+
+            fn $method(self) -> Self::Output {
+                $name(::core::ops::$trait::$method(self.0))
+            }
+        }
+
+        impl ::core::ops::$trait for &$name {
+            type Output = $name;
+
+            fn $method(self) -> Self::Output {
+                $name(::core::ops::$trait::$method(::core::clone::Clone::clone(&self.0)))
+            }
+        }
+    };
+    ($name:ident: $($operator:ident),* $(,)?) => {
+        $($crate::impl_ops!(@operator $name, $operator);)*
+    };
+}
+
+/// Returns the minimum of the arguments.
+///
+/// It compares them with [`PartialOrd`] and returns the first one on ties. Each
+/// argument is evaluated exactly once.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::min;
+///
+/// assert_eq!(min!(3), 3);
+/// assert_eq!(min!(3, 1, 2), 1);
+/// assert_eq!(min!(2.5, 0.5, 1.5), 0.5);
+/// assert_eq!(min!("b", "a", "c"), "a");
+/// ```
+#[macro_export]
+macro_rules! min {
+    ($first:expr $(, $rest:expr)* $(,)?) => {{
+        let value = $first;
+        $(let value = $crate::__private::min(value, $rest);)*
+        value
+    }};
+}
+
+/// Returns the maximum of the arguments.
+///
+/// It compares them with [`PartialOrd`] and returns the last one on ties. Each
+/// argument is evaluated exactly once.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::max;
+///
+/// assert_eq!(max!(3), 3);
+/// assert_eq!(max!(3, 1, 2), 3);
+/// assert_eq!(max!(2.5, 0.5, 1.5), 2.5);
+/// assert_eq!(max!("b", "a", "c"), "c");
+/// ```
+#[macro_export]
+macro_rules! max {
+    ($first:expr $(, $rest:expr)* $(,)?) => {{
+        let value = $first;
+        $(let value = $crate::__private::max(value, $rest);)*
+        value
+    }};
+}
+
+/// Adds the arguments.
+///
+/// With a leading type, each argument is converted to it with `as` first. Each
+/// argument is evaluated exactly once, and this works in constant contexts.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::sum;
+///
+/// const TOTAL: u32 = sum!(1, 2, 3);
+/// assert_eq!(TOTAL, 6);
+///
+/// let bytes: [u8; 3] = [200, 100, 50];
+/// assert_eq!(sum!(u64: bytes[0], bytes[1], bytes[2]), 350);
+/// assert_eq!(sum!(u64:), 0);
+/// ```
+#[macro_export]
+macro_rules! sum {
+    ($first:expr $(, $rest:expr)* $(,)?) => {
+        ($first $(+ $rest)*)
+    };
+    ($type:ty: $($value:expr),* $(,)?) => {
+        (0 as $type $(+ ($value) as $type)*)
+    };
+}
+
+/// Multiplies the arguments.
+///
+/// With a leading type, each argument is converted to it with `as` first. Each
+/// argument is evaluated exactly once, and this works in constant contexts.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::product;
+///
+/// const TOTAL: u32 = product!(2, 3, 4);
+/// assert_eq!(TOTAL, 24);
+///
+/// let bytes: [u8; 3] = [200, 100, 50];
+/// assert_eq!(product!(u64: bytes[0], bytes[1], bytes[2]), 1_000_000);
+/// assert_eq!(product!(u64:), 1);
+/// ```
+#[macro_export]
+macro_rules! product {
+    ($first:expr $(, $rest:expr)* $(,)?) => {
+        ($first $(* $rest)*)
+    };
+    ($type:ty: $($value:expr),* $(,)?) => {
+        (1 as $type $(* ($value) as $type)*)
+    };
+}
+
+/// Evaluates a chain of comparisons like `low <= x < high`.
+///
+/// Each operand is evaluated at most once, from left to right, and the chain
+/// stops at the first comparison that fails. Operands containing comparison
+/// operators must be parenthesized.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::within;
+///
+/// let mut calls = 0;
+/// let mut value = || {
+///     calls += 1;
+///     5
+/// };
+///
+/// assert!(within!(0 <= value() < 10));
+/// assert!(!within!(6 <= value() < 10));
+/// assert_eq!(calls, 2);
+///
+/// assert!(within!(1 < 2 <= 2 < 3 != 4));
+/// assert!(!within!(3 > 2 > 2));
+/// assert!(within!(0 <= (if 1 < 2 { 1 } else { 2 }) == 1));
+/// ```
+#[macro_export]
+macro_rules! within {
+    (@parse [$($chain:tt)*] [$($operand:tt)+] < $($rest:tt)+) => {
+        $crate::within!(@parse [$($chain)* ($($operand)+) <] [] $($rest)+)
+    };
+    (@parse [$($chain:tt)*] [$($operand:tt)+] <= $($rest:tt)+) => {
+        $crate::within!(@parse [$($chain)* ($($operand)+) <=] [] $($rest)+)
+    };
+    (@parse [$($chain:tt)*] [$($operand:tt)+] > $($rest:tt)+) => {
+        $crate::within!(@parse [$($chain)* ($($operand)+) >] [] $($rest)+)
+    };
+    (@parse [$($chain:tt)*] [$($operand:tt)+] >= $($rest:tt)+) => {
+        $crate::within!(@parse [$($chain)* ($($operand)+) >=] [] $($rest)+)
+    };
+    (@parse [$($chain:tt)*] [$($operand:tt)+] == $($rest:tt)+) => {
+        $crate::within!(@parse [$($chain)* ($($operand)+) ==] [] $($rest)+)
+    };
+    (@parse [$($chain:tt)*] [$($operand:tt)+] != $($rest:tt)+) => {
+        $crate::within!(@parse [$($chain)* ($($operand)+) !=] [] $($rest)+)
+    };
+    (@parse [$($chain:tt)*] [$($operand:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::within!(@parse [$($chain)*] [$($operand)* $token] $($rest)*)
+    };
+    (@parse [($($first:tt)+) $($chain:tt)+] [$($operand:tt)+]) => {{
+        let left = ($($first)+);
+        $crate::within!(@chain left, $($chain)+ ($($operand)+))
+    }};
+    (@parse [$($chain:tt)*] [$($operand:tt)*]) => {
+        ::core::compile_error!("expected a chain of comparisons")
+    };
+    (@chain $left:ident, $operator:tt ($($right:tt)+)) => {
+        $left $operator ($($right)+)
+    };
+    (@chain $left:ident, $operator:tt ($($right:tt)+) $($rest:tt)+) => {{
+        let right = ($($right)+);
+        $left $operator right && $crate::within!(@chain right, $($rest)+)
+    }};
+    ($($token:tt)+) => {
+        $crate::within!(@parse [] [] $($token)+)
+    };
+}
+
+/// Takes the value of a place, leaving the default value.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::take;
+///
+/// struct Buffer {
+///     lines: Vec<&'static str>,
+/// }
+///
+/// impl Buffer {
+///     fn flush(&mut self) -> Vec<&'static str> {
+///         take!(self.lines)
+///     }
+/// }
+///
+/// let mut buffer = Buffer {
+///     lines: vec!["a", "b"],
+/// };
+/// assert_eq!(buffer.flush(), ["a", "b"]);
+/// assert!(buffer.lines.is_empty());
+/// ```
+#[macro_export]
+macro_rules! take {
+    ($place:expr $(,)?) => {
+        ::core::mem::take(&mut $place)
+    };
+}
+
+/// Replaces the value of a place, returning the previous one.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::replace;
+///
+/// let mut values = [1, 2, 3];
+/// assert_eq!(replace!(values[1], 5), 2);
+/// assert_eq!(values, [1, 5, 3]);
+/// ```
+#[macro_export]
+macro_rules! replace {
+    ($place:expr, $value:expr $(,)?) => {
+        ::core::mem::replace(&mut $place, $value)
+    };
+}
+
+/// Replaces the value of a place until the end of the scope.
+///
+/// It returns a [`Guard`] dereferencing to the mutable reference to the place,
+/// which puts the previous value back when dropped.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::swap_scoped;
+///
+/// let mut depth = 0;
+///
+/// {
+///     let mut depth = swap_scoped!(depth, 5);
+///     **depth += 1;
+///     assert_eq!(**depth, 6);
+/// }
+///
+/// assert_eq!(depth, 0);
+/// ```
+#[macro_export]
+macro_rules! swap_scoped {
+    ($place:expr, $value:expr $(,)?) => {{
+        let value = $value;
+        let place = &mut $place;
+        let previous = ::core::mem::replace(place, value);
+        $crate::Guard::new(place, move |place: &mut _| *place = previous)
+    }};
+}
+
+/// Creates a map from key-value pairs.
+///
+/// It creates a [`BTreeMap`](alloc::collections::BTreeMap) by default, or a
+/// [`HashMap`](std::collections::HashMap) with the capacity reserved when the
+/// pairs are prefixed with `HashMap:`. This requires the `alloc` feature, and
+/// `HashMap` requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::map,
+///     std::collections::{
+///         BTreeMap,
+///         HashMap,
+///     },
+/// };
+///
+/// let ordered: BTreeMap<&str, i32> = map! { "b" => 2, "a" => 1 };
+/// assert_eq!(
+///     ordered.into_iter().collect::<Vec<_>>(),
+///     [("a", 1), ("b", 2)]
+/// );
+///
+/// let hashed: HashMap<&str, i32> = map! { HashMap: "a" => 1, "b" => 2 };
+/// assert_eq!(hashed["b"], 2);
+/// assert!(hashed.capacity() >= 2);
+/// ```
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! map {
+    (HashMap: $($key:expr => $value:expr),* $(,)?) => {{
+        let mut map = $crate::__private::HashMap::with_capacity(<[()]>::len(&[$({
+            ::core::stringify!($key);
+        }),*]));
+
+        $($crate::__private::HashMap::insert(&mut map, $key, $value);)*
+        map
+    }};
+    (BTreeMap: $($key:expr => $value:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut map = $crate::__private::BTreeMap::new();
+        $($crate::__private::BTreeMap::insert(&mut map, $key, $value);)*
+        map
+    }};
+    ($($key:expr => $value:expr),* $(,)?) => {
+        $crate::map!(BTreeMap: $($key => $value),*)
+    };
+}
+
+/// Creates a set from values.
+///
+/// It creates a [`BTreeSet`](alloc::collections::BTreeSet) by default, or a
+/// [`HashSet`](std::collections::HashSet) with the capacity reserved when the
+/// values are prefixed with `HashSet:`. This requires the `alloc` feature, and
+/// `HashSet` requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::set,
+///     std::collections::{
+///         BTreeSet,
+///         HashSet,
+///     },
+/// };
+///
+/// let ordered: BTreeSet<i32> = set! { 3, 1, 2, 1 };
+/// assert_eq!(ordered.into_iter().collect::<Vec<_>>(), [1, 2, 3]);
+///
+/// let hashed: HashSet<&str> = set! { HashSet: "a", "b" };
+/// assert!(hashed.contains("a"));
+/// assert!(hashed.capacity() >= 2);
+/// ```
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! set {
+    (HashSet: $($value:expr),* $(,)?) => {{
+        let mut set = $crate::__private::HashSet::with_capacity(<[()]>::len(&[$({
+            ::core::stringify!($value);
+        }),*]));
+
+        $($crate::__private::HashSet::insert(&mut set, $value);)*
+        set
+    }};
+    (BTreeSet: $($value:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut set = $crate::__private::BTreeSet::new();
+        $($crate::__private::BTreeSet::insert(&mut set, $value);)*
+        set
+    }};
+    ($($value:expr),* $(,)?) => {
+        $crate::set!(BTreeSet: $($value),*)
+    };
+}
+
+/// Creates an array by calling a closure with each index.
+///
+/// The element type does not need to implement [`Copy`] or [`Default`]. If the
+/// closure panics, the elements created so far are dropped. Without a length,
+/// it is inferred.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::array_init;
+///
+/// let doubled = array_init!(4, |index| index * 2);
+/// assert_eq!(doubled, [0, 2, 4, 6]);
+///
+/// let names: [String; 3] = array_init!(|index| format!("item{index}"));
+/// assert_eq!(names, ["item0", "item1", "item2"]);
+/// ```
+///
+/// ```
+/// use {
+///     keymacro::{
+///         Defer,
+///         array_init,
+///     },
+///     std::{
+///         cell::Cell,
+///         panic,
+///     },
+/// };
+///
+/// let dropped = Cell::new(0);
+///
+/// let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+///     array_init!(4, |index| {
+///         assert!(index < 2);
+///         Defer::new(|| Cell::set(&dropped, Cell::get(&dropped) + 1))
+///     })
+/// }));
+///
+/// assert!(result.is_err());
+/// assert_eq!(Cell::get(&dropped), 2);
+/// ```
+#[macro_export]
+macro_rules! array_init {
+    ($length:expr, $init:expr $(,)?) => {
+        $crate::__private::array_init::<_, { $length }, _>($init)
+    };
+    ($init:expr $(,)?) => {
+        $crate::__private::array_init($init)
+    };
+}
+
+/// Creates a [`Duration`](core::time::Duration) from amounts with units at
+/// compile time.
+///
+/// The units are `d`, `h`, `min`, `s`, `ms`, `us` and `ns`.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::duration,
+///     std::time::Duration,
+/// };
+///
+/// assert_eq!(duration!(2 s 500 ms), Duration::from_millis(2500));
+/// assert_eq!(duration!(90 min), Duration::from_secs(5400));
+/// assert_eq!(duration!(1 d 1 h 1 us 1 ns), Duration::new(90000, 1001));
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::duration;
+///
+/// duration!(1 week);
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::duration;
+///
+/// duration!(18446744073709551615 d);
+/// ```
+#[macro_export]
+macro_rules! duration {
+    (@unit d) => { 86_400_000_000_000 };
+    (@unit h) => { 3_600_000_000_000 };
+    (@unit min) => { 60_000_000_000 };
+    (@unit s) => { 1_000_000_000 };
+    (@unit ms) => { 1_000_000 };
+    (@unit us) => { 1_000 };
+    (@unit ns) => { 1 };
+    (@unit $unit:ident) => {
+        ::core::compile_error!(::core::concat!("unknown unit `", ::core::stringify!($unit), "`"))
+    };
+    ($($value:literal $unit:ident)+) => {{
+        const DURATION: ::core::time::Duration =
+            $crate::__private::duration(&[$(($value, $crate::duration!(@unit $unit))),+]);
+
+        DURATION
+    }};
+}
+
+/// Creates a byte count from amounts with units at compile time.
+///
+/// The units are `B`, the binary `KiB`, `MiB`, `GiB`, `TiB`, `PiB` and `EiB`,
+/// and the decimal `kB`, `MB`, `GB`, `TB`, `PB` and `EB`. The count is a
+/// [`usize`] unless an integer type is given first.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::bytesize;
+///
+/// assert_eq!(bytesize!(4 MiB), 4 * 1024 * 1024);
+/// assert_eq!(bytesize!(4 MB), 4_000_000);
+/// assert_eq!(bytesize!(1 KiB 512 B), 1536);
+/// assert_eq!(bytesize!(u64: 15 EiB), 15 << 60);
+/// assert_eq!(bytesize!(u64: 8 GiB), 8 << 30);
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::bytesize;
+///
+/// bytesize!(u32: 4 GiB);
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::bytesize;
+///
+/// bytesize!(4 mb);
+/// ```
+#[macro_export]
+macro_rules! bytesize {
+    (@unit B) => { 1 };
+    (@unit KiB) => { 1 << 10 };
+    (@unit MiB) => { 1 << 20 };
+    (@unit GiB) => { 1 << 30 };
+    (@unit TiB) => { 1 << 40 };
+    (@unit PiB) => { 1 << 50 };
+    (@unit EiB) => { 1 << 60 };
+    (@unit kB) => { 1_000 };
+    (@unit MB) => { 1_000_000 };
+    (@unit GB) => { 1_000_000_000 };
+    (@unit TB) => { 1_000_000_000_000 };
+    (@unit PB) => { 1_000_000_000_000_000 };
+    (@unit EB) => { 1_000_000_000_000_000_000 };
+    (@unit $unit:ident) => {
+        ::core::compile_error!(::core::concat!("unknown unit `", ::core::stringify!($unit), "`"))
+    };
+    ($type:ty: $($value:literal $unit:ident)+) => {{
+        const BYTES: $type = {
+            let bytes = $crate::__private::bytesize(&[$(($value, $crate::bytesize!(@unit $unit))),+]);
+
+            if bytes > <$type>::MAX as u128 {
+                panic!("the byte count overflows");
+            }
+
+            bytes as $type
+        };
+
+        BYTES
+    }};
+    ($($value:literal $unit:ident)+) => {
+        $crate::bytesize!(usize: $($value $unit)+)
+    };
+}
+
+/// Parses an IP address or a socket address at compile time.
+///
+/// An address expands to an [`IpAddr`](core::net::IpAddr) and an address with
+/// a port expands to a [`SocketAddr`](core::net::SocketAddr). Both can be used
+/// in constant contexts. A malformed address fails the build.
+///
+/// This requires the `ip` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::ip,
+///     std::net::{
+///         IpAddr,
+///         Ipv4Addr,
+///         Ipv6Addr,
+///         SocketAddr,
+///     },
+/// };
+///
+/// const LOCALHOST: IpAddr = ip!("127.0.0.1");
+/// const SERVER: SocketAddr = ip!("[::1]:8080");
+///
+/// assert_eq!(LOCALHOST, IpAddr::V4(Ipv4Addr::LOCALHOST));
+/// assert_eq!(ip!("::1"), IpAddr::V6(Ipv6Addr::LOCALHOST));
+/// assert_eq!(SERVER.ip(), IpAddr::V6(Ipv6Addr::LOCALHOST));
+/// assert_eq!(SERVER.port(), 8080);
+/// assert_eq!(
+///     ip!("192.168.1.1:80"),
+///     "192.168.1.1:80".parse::<SocketAddr>().unwrap()
+/// );
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::ip;
+///
+/// ip!("256.0.0.1");
+/// ```
+#[cfg(feature = "ip")]
+#[macro_export]
+macro_rules! ip {
+    ($text:expr $(,)?) => {{
+        struct Text;
+
+        impl $crate::__private::AddressText for Text {
+            const TEXT: &'static str = $text;
+        }
+
+        const SOCKET: ::core::primitive::bool = $crate::__private::is_socket_address($text);
+        <$crate::__private::AddressKind<SOCKET> as $crate::__private::Address<Text>>::VALUE
+    }};
+}
+
+/// A semantic version.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::Version;
+///
+/// let version = Version {
+///     major: 1,
+///     minor: 4,
+///     patch: 0,
+///     pre: "rc.1",
+///     build: "",
+/// };
+///
+/// assert_eq!(version.to_string(), "1.4.0-rc.1");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Version {
+    /// The major version.
+    pub major: u64,
+    /// The minor version.
+    pub minor: u64,
+    /// The patch version.
+    pub patch: u64,
+    /// The pre-release identifiers, or an empty string.
+    pub pre: &'static str,
+    /// The build metadata, or an empty string.
+    pub build: &'static str,
+}
+
+impl core::fmt::Display for Version {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "{}.{}.{}", self.major, self.minor, self.patch)?;
+
+        if !self.pre.is_empty() {
+            write!(formatter, "-{}", self.pre)?;
+        }
+
+        if !self.build.is_empty() {
+            write!(formatter, "+{}", self.build)?;
+        }
+
+        Result::Ok(())
+    }
+}
+
+/// Parses a [`Version`] at compile time.
+///
+/// A malformed version fails the build.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::{
+///     Version,
+///     semver,
+/// };
+///
+/// const PROTOCOL: Version = semver!("1.4.0-rc.1");
+///
+/// assert_eq!((PROTOCOL.major, PROTOCOL.minor, PROTOCOL.patch), (1, 4, 0));
+/// assert_eq!(PROTOCOL.pre, "rc.1");
+/// assert_eq!(semver!("0.1.2+build.5").build, "build.5");
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::semver;
+///
+/// semver!("1.04.0");
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::semver;
+///
+/// semver!("1.4.0-");
+/// ```
+#[macro_export]
+macro_rules! semver {
+    ($text:expr $(,)?) => {{
+        const VERSION: $crate::Version = $crate::__private::parse_version($text);
+        VERSION
+    }};
+}
+
+/// Evaluates a setup expression, runs a block and then passes the setup value
+/// to a teardown closure.
+///
+/// The teardown closure runs even if the block returns early or panics. With
+/// `let`, the [`Guard`] owning the setup value is bound for the block.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::fn_guard,
+///     std::cell::RefCell,
+/// };
+///
+/// let log = RefCell::new(Vec::new());
+///
+/// let len = fn_guard!(
+///     let mut connection = String::from("connected"),
+///     |connection| RefCell::borrow_mut(&log).push(connection),
+///     {
+///         connection.push_str(" and used");
+///         connection.len()
+///     }
+/// );
+///
+/// fn_guard!(RefCell::borrow_mut(&log).push(String::from("setup")), |_| {
+///     RefCell::borrow_mut(&log).push(String::from("teardown"))
+/// }, {
+///     RefCell::borrow_mut(&log).push(String::from("body"));
+/// });
+///
+/// assert_eq!(len, 18);
+/// assert_eq!(*RefCell::borrow(&log), ["connected and used", "setup", "body", "teardown"]);
+/// ```
+#[macro_export]
+macro_rules! fn_guard {
+    (let $name:ident = $setup:expr, $teardown:expr, { $($body:tt)* } $(,)?) => {{
+        let $name = $crate::Guard::new($setup, $teardown);
+        $($body)*
+    }};
+    (let mut $name:ident = $setup:expr, $teardown:expr, { $($body:tt)* } $(,)?) => {{
+        let mut $name = $crate::Guard::new($setup, $teardown);
+        $($body)*
+    }};
+    ($setup:expr, $teardown:expr, { $($body:tt)* } $(,)?) => {{
+        let _guard = $crate::Guard::new($setup, $teardown);
+        $($body)*
+    }};
+}
+
+/// Runs statements with a cleanup block that runs even if they panic.
+///
+/// By default, the cleanup block runs in a [`Defer`] and the original panic
+/// keeps unwinding after it. With `catch`, the panic is caught instead and the
+/// macro returns a [`Result`] holding the panic payload. `catch` requires the
+/// `std` feature and runs the statements in a closure.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::with_cleanup,
+///     std::{
+///         cell::Cell,
+///         panic,
+///     },
+/// };
+///
+/// let cleaned = Cell::new(0);
+///
+/// let value = with_cleanup! {
+///     cleanup {
+///         Cell::set(&cleaned, Cell::get(&cleaned) + 1);
+///     }
+///
+///     assert_eq!(Cell::get(&cleaned), 0);
+///     1
+/// };
+///
+/// let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+///     with_cleanup! {
+///         cleanup {
+///             Cell::set(&cleaned, Cell::get(&cleaned) + 1);
+///         }
+///
+///         panic!("failed");
+///     }
+/// }));
+///
+/// assert_eq!(value, 1);
+/// assert!(result.is_err());
+/// assert_eq!(Cell::get(&cleaned), 2);
+/// ```
+///
+/// ```
+/// # #[cfg(feature = "std")] {
+/// use {
+///     keymacro::with_cleanup,
+///     std::cell::Cell,
+/// };
+///
+/// let cleaned = Cell::new(false);
+///
+/// let caught = with_cleanup! {
+///     cleanup catch {
+///         Cell::set(&cleaned, true);
+///     }
+///
+///     panic!("failed");
+/// };
+///
+/// assert_eq!(*caught.unwrap_err().downcast::<&str>().unwrap(), "failed");
+/// assert!(Cell::get(&cleaned));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! with_cleanup {
+    (cleanup catch { $($cleanup:tt)* } $($body:tt)*) => {
+        $crate::__require_std!("`catch` requires the `std` feature" {{
+            let result = $crate::__private::catch_unwind($crate::__private::AssertUnwindSafe(|| { $($body)* }));
+            $($cleanup)*
+            result
+        }})
+    };
+    (cleanup { $($cleanup:tt)* } $($body:tt)*) => {{
+        let _cleanup = $crate::Defer::new(|| { $($cleanup)* });
+        $($body)*
+    }};
+}
+
+/// Runs a block and catches a panic in it.
+///
+/// It returns a [`Result`] holding the value of the block or the panic
+/// payload. The block is run in a closure wrapped in
+/// [`AssertUnwindSafe`](std::panic::AssertUnwindSafe).
+///
+/// This requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::catch;
+///
+/// let mut values = vec![1, 2];
+///
+/// let pushed = catch! {
+///     values.push(3);
+///     values.len()
+/// };
+///
+/// let panicked = catch! {
+///     values.push(4);
+///     panic!("failed");
+/// };
+///
+/// assert_eq!(pushed.unwrap(), 3);
+/// assert_eq!(*panicked.unwrap_err().downcast::<&str>().unwrap(), "failed");
+/// assert_eq!(values, [1, 2, 3, 4]);
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! catch {
+    ($($token:tt)*) => {
+        $crate::__private::catch_unwind($crate::__private::AssertUnwindSafe(|| { $($token)* }))
+    };
+}
+
+/// A RAII aborting the process unless it is disarmed.
+///
+/// With the `std` feature, it calls [`std::process::abort`]. Otherwise it
+/// panics, which aborts if the thread is already panicking.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::AbortGuard;
+///
+/// extern "C" fn callback() {
+///     let mut guard = AbortGuard::new();
+///     // Code which must not unwind into the caller.
+///     AbortGuard::disarm(&mut guard);
+/// }
+///
+/// callback();
+/// ```
+#[must_use]
+pub struct AbortGuard {
+    disarmed: bool,
+}
+
+impl AbortGuard {
+    /// Creates a new instance.
+    pub const fn new() -> Self {
+        Self {
+            disarmed: false,
+        }
+    }
+
+    /// Disarms the guard.
+    pub fn disarm(&mut self) {
+        self.disarmed = true;
+    }
+}
+
+impl Default for AbortGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for AbortGuard {
+    fn drop(&mut self) {
+        if !self.disarmed {
+            #[cfg(feature = "std")]
+            std::process::abort();
+            #[cfg(not(feature = "std"))]
+            panic!("`AbortGuard` was dropped without `disarm`");
+        }
+    }
+}
+
+/// Runs a block and aborts the process if it panics.
+///
+/// The block is run in a closure guarded by an [`AbortGuard`].
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::abort_on_panic;
+///
+/// extern "C" fn callback(value: i32) -> i32 {
+///     abort_on_panic! {
+///         value.checked_mul(2).unwrap_or(0)
+///     }
+/// }
+///
+/// assert_eq!(callback(2), 4);
+/// ```
+#[macro_export]
+macro_rules! abort_on_panic {
+    ($($token:tt)*) => {{
+        let mut guard = $crate::AbortGuard::new();
+        let value = (|| { $($token)* })();
+        $crate::AbortGuard::disarm(&mut guard);
+        value
+    }};
+}
+
+/// Pushes a block to a [`DeferStack`] so that it runs before every other
+/// closure of the stack, including ones pushed later.
+///
+/// Blocks pushed by this run in the reverse order of pushing among themselves.
+///
+/// This requires the `alloc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::{
+///         DeferStack,
+///         defer_first,
+///     },
+///     std::cell::RefCell,
+/// };
+///
+/// let order = RefCell::new(Vec::new());
+///
+/// {
+///     let mut stack = DeferStack::new();
+///
+///     defer_first!(stack, {
+///         RefCell::borrow_mut(&order).push("first");
+///     });
+///
+///     stack.push(|| RefCell::borrow_mut(&order).push("second"));
+/// }
+///
+/// assert_eq!(*RefCell::borrow(&order), ["first", "second"]);
+/// ```
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! defer_first {
+    ($stack:expr, move { $($token:tt)* } $(,)?) => {
+        $crate::DeferStack::push_with_priority(&mut $stack, ::core::primitive::i32::MAX, move || { $($token)* })
+    };
+    ($stack:expr, { $($token:tt)* } $(,)?) => {
+        $crate::DeferStack::push_with_priority(&mut $stack, ::core::primitive::i32::MAX, || { $($token)* })
+    };
+}
+
+/// Pins local variables in place, shadowing them with
+/// [`Pin<&mut T>`](core::pin::Pin).
+///
+/// Unlike [`keep_pinned!`], it does not rely on [`core::pin::pin!`].
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::pin_local,
+///     std::{
+///         future::Future,
+///         task::{
+///             Context,
+///             Poll,
+///             Waker,
+///         },
+///     },
+/// };
+///
+/// let first = async { 1 };
+/// let second = async { 2 };
+/// pin_local!(first, second);
+///
+/// let mut context = Context::from_waker(Waker::noop());
+/// assert_eq!(Future::poll(first.as_mut(), &mut context), Poll::Ready(1));
+/// assert_eq!(Future::poll(second, &mut context), Poll::Ready(2));
+/// ```
+#[macro_export]
+macro_rules! pin_local {
+    ($($name:ident),+ $(,)?) => {
+        $(
+            let mut $name = $name;
+
+            // SAFETY: The value is shadowed, so it is never moved again.
+            #[allow(unused_mut)]
+            let mut $name = unsafe { ::core::pin::Pin::new_unchecked(&mut $name) };
+        )+
+    };
+}
+
+/// Evaluates a block if a chain of patterns and conditions all hold.
+///
+/// Conditions are separated by `,`, and names bound by a `let` condition are
+/// visible in the later conditions and in the block. The optional `else` block
+/// runs if any condition fails.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::let_chain;
+///
+/// fn compare(x: Option<i32>, y: Result<i32, ()>) -> &'static str {
+///     let_chain!(let Some(a) = x, let Ok(b) = y, a > b => {
+///         "greater"
+///     } else {
+///         "other"
+///     })
+/// }
+///
+/// assert_eq!(compare(Some(2), Ok(1)), "greater");
+/// assert_eq!(compare(Some(1), Ok(2)), "other");
+/// assert_eq!(compare(None, Ok(1)), "other");
+///
+/// let mut found = None;
+///
+/// let_chain!(let Some(value) = "1".parse::<i32>().ok(), value > 0 => {
+///     found = Some(value);
+/// });
+///
+/// assert_eq!(found, Some(1));
+/// ```
+#[macro_export]
+macro_rules! let_chain {
+    (@munch [$($condition:tt)*] let $pattern:pat = $value:expr, $($rest:tt)+) => {
+        $crate::let_chain!(@munch [$($condition)* (let $pattern = $value)] $($rest)+)
+    };
+    (@munch [$($condition:tt)*] let $pattern:pat = $value:expr => $then:block $(else $otherwise:block)?) => {
+        $crate::let_chain!(@build [$($condition)* (let $pattern = $value)] $then $($otherwise)?)
+    };
+    (@munch [$($condition:tt)*] $test:expr, $($rest:tt)+) => {
+        $crate::let_chain!(@munch [$($condition)* ($test)] $($rest)+)
+    };
+    (@munch [$($condition:tt)*] $test:expr => $then:block $(else $otherwise:block)?) => {
+        $crate::let_chain!(@build [$($condition)* ($test)] $then $($otherwise)?)
+    };
+    (@build [$($condition:tt)*] $then:block $($otherwise:block)?) => {
+        'chain: {
+            $crate::let_chain!(@nest 'chain $then $($condition)*);
+            $($otherwise)?
+        }
+    };
+    (@nest $label:lifetime $then:block) => {
+        break $label $then
+    };
+    (@nest $label:lifetime $then:block (let $pattern:pat = $value:expr) $($rest:tt)*) => {
+        if let $pattern = $value {
+            $crate::let_chain!(@nest $label $then $($rest)*);
+        }
+    };
+    (@nest $label:lifetime $then:block ($test:expr) $($rest:tt)*) => {
+        if $test {
+            $crate::let_chain!(@nest $label $then $($rest)*);
+        }
+    };
+    ($($token:tt)+) => {
+        $crate::let_chain!(@munch [] $($token)+)
+    };
+}
+
+/// Evaluates the expression of the first arm whose condition holds.
+///
+/// It expands to an `if` chain, and `_` matches when no other condition holds.
+/// Without `_`, the arms must evaluate to `()`.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::cond;
+///
+/// fn classify(value: i32) -> &'static str {
+///     cond! {
+///         value < 0 => "negative",
+///         value == 0 => "zero",
+///         value % 2 == 0 => {
+///             "even"
+///         }
+///         value % 3 == 0 => {
+///             "multiple of three"
+///         },
+///         _ => "odd",
+///     }
+/// }
+///
+/// assert_eq!(classify(-1), "negative");
+/// assert_eq!(classify(0), "zero");
+/// assert_eq!(classify(2), "even");
+/// assert_eq!(classify(3), "multiple of three");
+/// assert_eq!(classify(5), "odd");
+///
+/// let mut log = Vec::new();
+///
+/// cond! {
+///     log.is_empty() => log.push("empty"),
+/// }
+///
+/// assert_eq!(log, ["empty"]);
+/// ```
+#[macro_export]
+macro_rules! cond {
+    (_ => $value:expr $(,)?) => {
+        $value
+    };
+    ($condition:expr => $value:block $(,)?) => {
+        if $condition $value
+    };
+    ($condition:expr => $value:block, $($rest:tt)+) => {
+        if $condition $value else { $crate::cond!($($rest)+) }
+    };
+    ($condition:expr => $value:block $($rest:tt)+) => {
+        if $condition $value else { $crate::cond!($($rest)+) }
+    };
+    ($condition:expr => $value:expr $(,)?) => {
+        if $condition { $value }
+    };
+    ($condition:expr => $value:expr, $($rest:tt)+) => {
+        if $condition { $value } else { $crate::cond!($($rest)+) }
+    };
+}
+
+/// Matches a value against arms like `match`.
+///
+/// An arm can also be only a guard, written as `if condition`, which matches
+/// any value when the condition holds. Arms with blocks do not need commas.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::switch;
+///
+/// fn describe(value: i32, strict: bool) -> &'static str {
+///     switch!(value;
+///         0..=9 => "digit",
+///         if strict => "rejected",
+///         10 | 20 => {
+///             "round"
+///         }
+///         value if value < 0 => "negative",
+///         _ => "large",
+///     )
+/// }
+///
+/// assert_eq!(describe(5, true), "digit");
+/// assert_eq!(describe(10, true), "rejected");
+/// assert_eq!(describe(10, false), "round");
+/// assert_eq!(describe(-1, false), "negative");
+/// assert_eq!(describe(100, false), "large");
+///
+/// let kind = switch!("bar"; "foo" | "bar" => 1, _ => 0);
+/// assert_eq!(kind, 1);
+/// ```
+#[macro_export]
+macro_rules! switch {
+    (@arms [$value:expr] [$($arm:tt)*] , $($rest:tt)*) => {
+        $crate::switch!(@arms [$value] [$($arm)*] $($rest)*)
+    };
+    (@arms [$value:expr] [$($arm:tt)*] if $guard:expr => $body:block $($rest:tt)*) => {
+        $crate::switch!(@arms [$value] [$($arm)* _ if $guard => $body,] $($rest)*)
+    };
+    (@arms [$value:expr] [$($arm:tt)*] if $guard:expr => $body:expr $(, $($rest:tt)*)?) => {
+        $crate::switch!(@arms [$value] [$($arm)* _ if $guard => $body,] $($($rest)*)?)
+    };
+    (@arms [$value:expr] [$($arm:tt)*] $pattern:pat $(if $guard:expr)? => $body:block $($rest:tt)*) => {
+        $crate::switch!(@arms [$value] [$($arm)* $pattern $(if $guard)? => $body,] $($rest)*)
+    };
+    (@arms [$value:expr] [$($arm:tt)*] $pattern:pat $(if $guard:expr)? => $body:expr $(, $($rest:tt)*)?) => {
+        $crate::switch!(@arms [$value] [$($arm)* $pattern $(if $guard)? => $body,] $($($rest)*)?)
+    };
+    (@arms [$value:expr] [$($arm:tt)*]) => {
+        match $value {
+            $($arm)*
+        }
+    };
+    ($value:expr; $($arm:tt)*) => {
+        $crate::switch!(@arms [$value] [] $($arm)*)
+    };
+}
+
+/// Selects the first branch whose `cfg` predicate holds.
+///
+/// Each branch is compiled only if its predicate holds and no earlier one
+/// does, and `_` matches when no predicate holds. At item position the
+/// branches hold items. At expression position the branches are wrapped in
+/// another pair of braces.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::cfg_match;
+///
+/// cfg_match! {
+///     cfg(unix) => {
+///         fn family() -> &'static str {
+///             "unix"
+///         }
+///     },
+///     cfg(windows) => {
+///         fn family() -> &'static str {
+///             "windows"
+///         }
+///     },
+///     _ => {
+///         fn family() -> &'static str {
+///             "other"
+///         }
+///     },
+/// }
+///
+/// let bits = cfg_match! {{
+///     cfg(target_pointer_width = "64") => { 64 },
+///     cfg(target_pointer_width = "32") => { 32 },
+///     _ => { 16 },
+/// }};
+///
+/// assert_eq!(
+///     family(),
+///     if cfg!(unix) {
+///         "unix"
+///     } else if cfg!(windows) {
+///         "windows"
+///     } else {
+///         "other"
+///     }
+/// );
+/// assert_eq!(bits, usize::BITS);
+/// ```
+#[macro_export]
+macro_rules! cfg_match {
+    (@body $($body:tt)*) => {
+        $($body)*
+    };
+    (@arms [$($previous:meta),*] $(,)?) => {};
+    (@arms [$($previous:meta),*] , $($rest:tt)+) => {
+        $crate::cfg_match! { @arms [$($previous),*] $($rest)+ }
+    };
+    (@arms [$($previous:meta),*] _ => { $($body:tt)* } $(,)?) => {
+        #[cfg(not(any($($previous),*)))]
+        $crate::cfg_match! { @body $($body)* }
+    };
+    (@arms [$($previous:meta),*] cfg($predicate:meta) => { $($body:tt)* } $($rest:tt)*) => {
+        #[cfg(all($predicate, not(any($($previous),*))))]
+        $crate::cfg_match! { @body $($body)* }
+        $crate::cfg_match! { @arms [$($previous,)* $predicate] $($rest)* }
+    };
+    ({ $($arms:tt)* }) => {{
+        $crate::cfg_match! { @arms [] $($arms)* }
+    }};
+    ($($arms:tt)*) => {
+        $crate::cfg_match! { @arms [] $($arms)* }
+    };
+}
+
+/// Emits a warning with a message at compile time.
+///
+/// The warning is reported as a use of a deprecated item whose note is the
+/// message. The macro can be used at both item and statement position.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::compile_warn;
+///
+/// compile_warn!("this configuration is slow");
+///
+/// fn main() {
+///     compile_warn!("unoptimized path");
+/// }
+/// ```
+#[macro_export]
+macro_rules! compile_warn {
+    ($message:literal $(,)?) => {
+        const _: () = {
+            #[deprecated(note = $message)]
+            struct CompileWarning;
+
+            let _ = CompileWarning;
+        };
+    };
+}
+
+/// Fails to compile after a date.
+///
+/// The date is written as `YYYY-MM-DD` and is compared with the date on
+/// which the invoking crate is compiled, or with `SOURCE_DATE_EPOCH` if it is
+/// set. An optional message is included in the error. The macro can be used at
+/// both item and statement position.
+///
+/// This requires the `todo_by` feature.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::todo_by;
+///
+/// todo_by!("2999-12-31", "remove the legacy path");
+///
+/// fn main() {
+///     todo_by!("2999-01-01");
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::todo_by;
+///
+/// todo_by!("2000-01-01", "remove the legacy path");
+/// ```
+#[cfg(feature = "todo_by")]
+pub use keymacro_macros::todo_by;
+
+/// Marks unreachable code, checked only in debug builds.
+///
+/// With `debug_assertions` this panics like `unreachable!` with the optional
+/// message. Without them it is `core::hint::unreachable_unchecked`, so the call
+/// must be wrapped in `unsafe`.
+///
+/// # Safety
+///
+/// Reaching this in a build without `debug_assertions` is undefined behavior.
+/// The caller must guarantee that it is never reached.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::unreachable_checked;
+///
+/// fn digit(value: u8) -> char {
+///     match value % 10 {
+///         value @ 0..=9 => char::from(b'0' + value),
+///         // SAFETY: `value % 10` is always less than 10.
+///         _ => unsafe { unreachable_checked!("{value} % 10 is a digit") },
+///     }
+/// }
+///
+/// assert_eq!(digit(42), '2');
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::unreachable_checked;
+///
+/// fn digit(value: u8) -> char {
+///     match value % 10 {
+///         value @ 0..=9 => char::from(b'0' + value),
+///         _ => unreachable_checked!(),
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! unreachable_checked {
+    ($($argument:tt)*) => {
+        if ::core::cfg!(debug_assertions) {
+            ::core::unreachable!($($argument)*)
+        } else {
+            $crate::__private::unreachable_unchecked()
+        }
+    };
+}
+
+/// Converts an integer to a narrower type, checked in debug builds.
+///
+/// With `debug_assertions` this panics with the value and the target type if
+/// the value does not fit. Without them it is a plain `as` cast, or a
+/// saturating cast if `saturating:` is given first. Everything before the last
+/// `as` is the value, so an operation must be in parentheses to read as Rust.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::narrow;
+///
+/// let value: u32 = 200;
+/// let small: i64 = -5;
+///
+/// assert_eq!(narrow!(value as u8), 200);
+/// assert_eq!(narrow!(small as i16), -5);
+/// assert_eq!(narrow!(saturating: (value + 1) as u8), 201);
+/// ```
+///
+/// Saturation happens only without `debug_assertions`, so this example checks
+/// the release behavior. Doctests keep `debug_assertions` even with
+/// `--release`, so it runs with `RUSTDOCFLAGS="-C debug-assertions=off"`.
+///
+/// ```
+/// use keymacro::narrow;
+///
+/// let large: u32 = 300;
+/// let negative: i32 = -1;
+///
+/// if !cfg!(debug_assertions) {
+///     assert_eq!(narrow!(saturating: (large + 1) as u8), u8::MAX);
+///     assert_eq!(narrow!(saturating: negative as u16), 0);
+///     assert_eq!(narrow!(large as u8), 44);
+/// }
+/// ```
+///
+/// ```should_panic
+/// use keymacro::narrow;
+///
+/// let value: u32 = 300;
+/// narrow!(value as u8);
+/// ```
+#[macro_export]
+macro_rules! narrow {
+    (@value [$($mode:ident)?] [$($value:tt)+] as $type:ty) => {
+        $crate::__private::narrow(
+            $($value)+,
+            ::core::cfg!(debug_assertions),
+            ::core::stringify!($type),
+            $crate::narrow!(@cast [$($mode)?] $type),
+        )
+    };
+    (@value [$($mode:ident)?] [$($value:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::narrow!(@value [$($mode)?] [$($value)* $token] $($rest)*)
+    };
+    (@cast [] $type:ty) => {
+        |value| value as $type
+    };
+    (@cast [saturating] $type:ty) => {
+        |value| match <$type as ::core::convert::TryFrom<_>>::try_from(value) {
+            ::core::result::Result::Ok(value) => value,
+            ::core::result::Result::Err(_) if $crate::__private::is_negative(&value) => <$type>::MIN,
+            ::core::result::Result::Err(_) => <$type>::MAX,
+        }
+    };
+    (saturating: $($value:tt)+) => {
+        $crate::narrow!(@value [saturating] [] $($value)+)
+    };
+    ($($value:tt)+) => {
+        $crate::narrow!(@value [] [] $($value)+)
+    };
+}
+
+/// Evaluates arithmetic with saturating operators.
+///
+/// The operators `+`, `-`, `*`, `/`, `%` and the unary `-` outside of calls
+/// and indices are replaced with their saturating forms. Parenthesized
+/// subexpressions are rewritten as well.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::saturating;
+///
+/// let a: u8 = 200;
+/// let b: u8 = 10;
+/// let c: u8 = 3;
+///
+/// assert_eq!(saturating!(a + b * c), 230);
+/// assert_eq!(saturating!(a + b * c * 2), 255);
+/// assert_eq!(saturating!(b - a), 0);
+/// assert_eq!(saturating!((a - b * 30) * c + 1), 1);
+/// assert_eq!(saturating!(-i8::MIN + 1), i8::MAX);
+/// ```
+#[macro_export]
+macro_rules! saturating {
+    (@munch [$wrapper:path] [$($output:tt)*] [] - $($rest:tt)*) => {
+        $crate::saturating!(@munch [$wrapper] [$($output)* -] [] $($rest)*)
+    };
+    (@munch [$wrapper:path] [$($output:tt)*] [] ($($inner:tt)*) $($rest:tt)*) => {
+        $crate::saturating!(
+            @munch [$wrapper] [$($output)*] [($crate::saturating!(@wrap [$wrapper] $($inner)*))] $($rest)*
+        )
+    };
+    (@munch [$wrapper:path] [$($output:tt)*] [$($operand:tt)+] + $($rest:tt)*) => {
+        $crate::saturating!(@munch [$wrapper] [$($output)* $wrapper($($operand)+) +] [] $($rest)*)
+    };
+    (@munch [$wrapper:path] [$($output:tt)*] [$($operand:tt)+] - $($rest:tt)*) => {
+        $crate::saturating!(@munch [$wrapper] [$($output)* $wrapper($($operand)+) -] [] $($rest)*)
+    };
+    (@munch [$wrapper:path] [$($output:tt)*] [$($operand:tt)+] * $($rest:tt)*) => {
+        $crate::saturating!(@munch [$wrapper] [$($output)* $wrapper($($operand)+) *] [] $($rest)*)
+    };
+    (@munch [$wrapper:path] [$($output:tt)*] [$($operand:tt)+] / $($rest:tt)*) => {
+        $crate::saturating!(@munch [$wrapper] [$($output)* $wrapper($($operand)+) /] [] $($rest)*)
+    };
+    (@munch [$wrapper:path] [$($output:tt)*] [$($operand:tt)+] % $($rest:tt)*) => {
+        $crate::saturating!(@munch [$wrapper] [$($output)* $wrapper($($operand)+) %] [] $($rest)*)
+    };
+    (@munch [$wrapper:path] [$($output:tt)*] [$($operand:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::saturating!(@munch [$wrapper] [$($output)*] [$($operand)* $token] $($rest)*)
+    };
+    (@munch [$wrapper:path] [$($output:tt)*] [$($operand:tt)+]) => {
+        ($($output)* $wrapper($($operand)+)).0
+    };
+    (@wrap [$wrapper:path] $($expression:tt)+) => {
+        $crate::saturating!(@munch [$wrapper] [] [] $($expression)+)
+    };
+    ($($expression:tt)+) => {
+        $crate::saturating!(@wrap [$crate::__private::Saturating] $($expression)+)
+    };
+}
+
+/// Evaluates arithmetic with wrapping operators.
+///
+/// The operators `+`, `-`, `*`, `/`, `%` and the unary `-` outside of calls
+/// and indices are replaced with their wrapping forms. Parenthesized
+/// subexpressions are rewritten as well.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::wrapping;
+///
+/// let a: u8 = 200;
+/// let b: u8 = 10;
+/// let c: u8 = 6;
+///
+/// assert_eq!(wrapping!(a + b * c), 4);
+/// assert_eq!(wrapping!(b - a), 66);
+/// assert_eq!(wrapping!((a + a) / 2), 72);
+/// assert_eq!(wrapping!(-i8::MIN), i8::MIN);
+/// ```
+#[macro_export]
+macro_rules! wrapping {
+    ($($expression:tt)+) => {
+        $crate::saturating!(@wrap [$crate::__private::Wrapping] $($expression)+)
+    };
+}
+
+/// Extracts or inserts a range of bits.
+///
+/// `word[high:low]` is the bits from `high` down to `low`, both inclusive,
+/// shifted down to bit `0`, and `word[bit]` is a single bit. With `= value`
+/// the range of the unsigned integer place is replaced with the value, which
+/// panics if the value does not fit. The range is checked against the width
+/// of the integer at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::bits;
+///
+/// let mut register: u16 = 0b1010_1100_0011_0101;
+///
+/// assert_eq!(bits!(register[11:4]), 0b1100_0011);
+/// assert_eq!(bits!(register[15]), 1);
+///
+/// bits!(register[3:0] = 0b1111);
+/// bits!(register[14] = 1);
+/// assert_eq!(register, 0b1110_1100_0011_1111);
+///
+/// let registers = [0u32, u32::MAX];
+/// assert_eq!(bits!(registers[1][31:16]), 0xFFFF);
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::bits;
+///
+/// let register: u8 = 0;
+/// bits!(register[8:0]);
+/// ```
+///
+/// ```should_panic
+/// use keymacro::bits;
+///
+/// let mut register: u8 = 0;
+/// bits!(register[1:0] = 4);
+/// ```
+#[macro_export]
+macro_rules! bits {
+    (@word [$($word:tt)+] [$high:tt : $low:tt]) => {
+        $crate::__private::extract_bits::<_, { $high }, { $low }>($($word)+)
+    };
+    (@word [$($word:tt)+] [$bit:tt]) => {
+        $crate::__private::extract_bits::<_, { $bit }, { $bit }>($($word)+)
+    };
+    (@word [$($word:tt)+] [$high:tt : $low:tt] = $value:expr) => {
+        $crate::__private::insert_bits::<_, { $high }, { $low }>(&mut $($word)+, $value)
+    };
+    (@word [$($word:tt)+] [$bit:tt] = $value:expr) => {
+        $crate::__private::insert_bits::<_, { $bit }, { $bit }>(&mut $($word)+, $value)
+    };
+    (@word [$($word:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::bits!(@word [$($word)* $token] $($rest)*)
+    };
+    ($($tokens:tt)+) => {
+        $crate::bits!(@word [] $($tokens)+)
+    };
+}
+
+/// Declares a set of bit flags.
+///
+/// The declaration is a transparent newtype over an integer with an associated
+/// constant for each flag. The set supports `|`, `&`, `^`, `-` and `!`, their
+/// assignment forms except for `!`, and methods like `contains`. [`Debug`]
+/// lists every contained flag and any remaining bits.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::flags;
+///
+/// flags! {
+///     #[derive(Clone, Copy, PartialEq, Eq)]
+///     pub struct Permissions: u8 {
+///         /// Allows reading.
+///         const READ = 1 << 0;
+///         const WRITE = 1 << 1;
+///         const EXECUTE = 1 << 2;
+///         const READ_WRITE = Self::READ.bits() | Self::WRITE.bits();
+///     }
+/// }
+///
+/// let mut permissions = Permissions::READ | Permissions::EXECUTE;
+///
+/// assert!(permissions.contains(Permissions::READ));
+/// assert!(!permissions.contains(Permissions::READ_WRITE));
+/// assert_eq!(permissions & Permissions::EXECUTE, Permissions::EXECUTE);
+/// assert_eq!(permissions.bits(), 0b101);
+///
+/// permissions |= Permissions::WRITE;
+/// permissions -= Permissions::EXECUTE;
+/// assert_eq!(permissions, Permissions::READ_WRITE);
+/// assert_eq!(!permissions, Permissions::EXECUTE);
+/// assert_eq!(Permissions::all().bits(), 0b111);
+/// assert_eq!(Permissions::from_bits(0b1000), None);
+///
+/// assert_eq!(
+///     format!("{permissions:?}"),
+///     "Permissions(READ | WRITE | READ_WRITE)"
+/// );
+/// assert_eq!(
+///     format!("{:?}", Permissions::from_bits_retain(0b1001)),
+///     "Permissions(READ | 0x8)"
+/// );
+/// assert_eq!(format!("{:?}", Permissions::empty()), "Permissions(0x0)");
+/// ```
+#[macro_export]
+macro_rules! flags {
+    (
+        $(#[$attribute:meta])*
+        $visibility:vis struct $name:ident: $bits:ty {
+            $(
+                $(#[$flag_attribute:meta])*
+                const $flag:ident = $value:expr;
+            )*
+        }
+    ) => {
+        $(#[$attribute])*
+        #[repr(transparent)]
+        $visibility struct $name($bits);
+
+        #[allow(dead_code)]
+        impl $name {
+            $(
+                $(#[$flag_attribute])*
+                pub const $flag: Self = Self($value);
+            )*
+
+            /// Returns the set of no flags.
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+
+            /// Returns the set of all flags.
+            pub const fn all() -> Self {
+                Self(0 $(| Self::$flag.0)*)
+            }
+
+            /// Returns the bits.
+            pub const fn bits(&self) -> $bits {
+                self.0
+            }
+
+            /// Creates a set from bits, or returns `None` if any bit is not a flag.
+            pub const fn from_bits(bits: $bits) -> ::core::option::Option<Self> {
+                if bits & !Self::all().0 == 0 {
+                    ::core::option::Option::Some(Self(bits))
+                } else {
+                    ::core::option::Option::None
+                }
+            }
+
+            /// Creates a set from bits, keeping any bit that is not a flag.
+            pub const fn from_bits_retain(bits: $bits) -> Self {
+                Self(bits)
+            }
+
+            /// Returns whether no flag is set.
+            pub const fn is_empty(&self) -> bool {
+                self.0 == 0
+            }
+
+            /// Returns whether all flags of `other` are set.
+            pub const fn contains(&self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            /// Returns whether any flag of `other` is set.
+            pub const fn intersects(&self, other: Self) -> bool {
+                self.0 & other.0 != 0
+            }
+
+            /// Sets the flags of `other`.
+            pub fn insert(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+
+            /// Clears the flags of `other`.
+            pub fn remove(&mut self, other: Self) {
+                self.0 &= !other.0;
+            }
+        }
+
+        impl ::core::ops::BitOr for $name {
+            type Output = Self;
+
+            fn bitor(self, other: Self) -> Self {
+                Self(self.0 | other.0)
+            }
+        }
+
+        impl ::core::ops::BitAnd for $name {
+            type Output = Self;
+
+            fn bitand(self, other: Self) -> Self {
+                Self(self.0 & other.0)
+            }
+        }
+
+        impl ::core::ops::BitXor for $name {
+            type Output = Self;
+
+            fn bitxor(self, other: Self) -> Self {
+                Self(self.0 ^ other.0)
+            }
+        }
+
+        impl ::core::ops::Sub for $name {
+            type Output = Self;
+
+            fn sub(self, other: Self) -> Self {
+                Self(self.0 & !other.0)
+            }
+        }
+
+        impl ::core::ops::Not for $name {
+            type Output = Self;
+
+            fn not(self) -> Self {
+                Self(!self.0 & Self::all().0)
+            }
+        }
+
+        impl ::core::ops::BitOrAssign for $name {
+            fn bitor_assign(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+        }
+
+        impl ::core::ops::BitAndAssign for $name {
+            fn bitand_assign(&mut self, other: Self) {
+                self.0 &= other.0;
+            }
+        }
+
+        impl ::core::ops::BitXorAssign for $name {
+            fn bitxor_assign(&mut self, other: Self) {
+                self.0 ^= other.0;
+            }
+        }
+
+        impl ::core::ops::SubAssign for $name {
+            fn sub_assign(&mut self, other: Self) {
+                self.0 &= !other.0;
+            }
+        }
+
+        impl ::core::fmt::Debug for $name {
+            fn fmt(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                $crate::__private::debug_flags(
+                    formatter,
+                    ::core::stringify!($name),
+                    self.0,
+                    &[$((::core::stringify!($flag), Self::$flag.0)),*],
+                )
+            }
+        }
+    };
+}
+
+/// Declares a fieldless enum with the list of its variants.
+///
+/// The enum gets a `COUNT` constant, an `ALL` array of the variants in
+/// declaration order, and the `from_index` and `as_index` methods, which
+/// convert between a variant and its position in `ALL`. Explicit
+/// discriminants do not affect the positions.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::enum_variants;
+///
+/// enum_variants! {
+///     #[derive(Debug, Clone, Copy, PartialEq)]
+///     pub enum State {
+///         /// Waiting for input.
+///         Idle = 10,
+///         Running,
+///         Done = 1,
+///     }
+/// }
+///
+/// assert_eq!(State::COUNT, 3);
+/// assert_eq!(State::ALL, [State::Idle, State::Running, State::Done]);
+/// assert_eq!(State::Done.as_index(), 2);
+/// assert_eq!(State::from_index(1), Some(State::Running));
+/// assert_eq!(State::from_index(3), None);
+///
+/// for (index, state) in State::ALL.into_iter().enumerate() {
+///     assert_eq!(state.as_index(), index);
+/// }
+/// ```
+#[macro_export]
+macro_rules! enum_variants {
+    (
+        $(#[$attribute:meta])*
+        $visibility:vis enum $name:ident {
+            $(
+                $(#[$variant_attribute:meta])*
+                $variant:ident $(= $discriminant:expr)?
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$attribute])*
+        $visibility enum $name {
+            $(
+                $(#[$variant_attribute])*
+                $variant $(= $discriminant)?,
+            )*
+        }
+
+        #[allow(dead_code)]
+        impl $name {
+            /// The number of the variants.
+            pub const COUNT: usize = [$(::core::stringify!($variant)),*].len();
+
+            /// The variants in declaration order.
+            pub const ALL: [Self; Self::COUNT] = [$(Self::$variant),*];
+
+            /// Returns the variant at a position in [`Self::ALL`].
+            pub const fn from_index(index: usize) -> ::core::option::Option<Self> {
+                #[allow(non_camel_case_types)]
+                enum Index {
+                    $($variant,)*
+                }
+
+                match index {
+                    $(index if index == Index::$variant as usize => {
+                        ::core::option::Option::Some(Self::$variant)
+                    },)*
+                    _ => ::core::option::Option::None,
+                }
+            }
+
+            /// Returns the position of the variant in [`Self::ALL`].
+            pub const fn as_index(&self) -> usize {
+                #[allow(non_camel_case_types)]
+                enum Index {
+                    $($variant,)*
+                }
+
+                match self {
+                    $(Self::$variant => Index::$variant as usize,)*
+                }
+            }
+        }
+    };
+}
+
+/// Declares an error enum with a message for each variant.
+///
+/// Each variant is followed by `=>` and its message, which is formatted with
+/// the fields bound by name, so the fields of a tuple variant are named like
+/// `Variant(name: Type)`. A single field written as `from name: Type` also
+/// implements [`From`] for the field type and is returned as the source of
+/// the error. The enum implements [`Display`](core::fmt::Display) and
+/// [`Error`](core::error::Error), which requires [`Debug`].
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::error_enum,
+///     std::{
+///         error::Error,
+///         num::ParseIntError,
+///     },
+/// };
+///
+/// error_enum! {
+///     #[derive(Debug)]
+///     pub enum ConfigError {
+///         /// The value is missing.
+///         Missing => "the value is missing",
+///         Parse(from error: ParseIntError) => "invalid number: {error}",
+///         Port(port: u32) => "port {port} is out of range",
+///         Range { min: u32, max: u32 } => "expected a value in {min}..={max}",
+///     }
+/// }
+///
+/// fn port(text: Option<&str>) -> Result<u16, ConfigError> {
+///     let port = text.ok_or(ConfigError::Missing)?.parse()?;
+///     u16::try_from(port).map_err(|_| ConfigError::Port(port))
+/// }
+///
+/// assert_eq!(port(Some("8080")).unwrap(), 8080);
+/// assert_eq!(port(None).unwrap_err().to_string(), "the value is missing");
+/// assert_eq!(
+///     port(Some("70000")).unwrap_err().to_string(),
+///     "port 70000 is out of range"
+/// );
+///
+/// let error = port(Some("x")).unwrap_err();
+/// assert_eq!(
+///     error.to_string(),
+///     "invalid number: invalid digit found in string"
+/// );
+/// assert!(error.source().is_some());
+///
+/// let error = ConfigError::Range {
+///     min: 1,
+///     max: 9,
+/// };
+/// assert_eq!(error.to_string(), "expected a value in 1..=9");
+/// assert!(error.source().is_none());
+/// ```
+#[macro_export]
+macro_rules! error_enum {
+    (@variants [$($header:tt)*] [$($variant:tt)*] [$($display:tt)*] [$($source:tt)*] [$($from:tt)*] $(,)?) => {
+        $crate::error_enum!(@declare [$($header)*] [$($variant)*] [$($display)*] [$($source)*] [$($from)*]);
+    };
+    (@variants [$($header:tt)*] [$($variant:tt)*] [$($display:tt)*] [$($source:tt)*] [$($from:tt)*] , $($rest:tt)+) => {
+        $crate::error_enum!(@variants [$($header)*] [$($variant)*] [$($display)*] [$($source)*] [$($from)*] $($rest)+);
+    };
+    (
+        @variants [$name:ident $formatter:ident $($header:tt)*] [$($variant:tt)*] [$($display:tt)*] [$($source:tt)*] [$($from:tt)*]
+        $(#[$attribute:meta])* $new:ident => $message:literal $($rest:tt)*
+    ) => {
+        $crate::error_enum! {
+            @variants [$name $formatter $($header)*]
+            [$($variant)* $(#[$attribute])* $new,]
+            [$($display)* Self::$new => ::core::write!($formatter, $message),]
+            [$($source)*]
+            [$($from)*]
+            $($rest)*
+        }
+    };
+    (
+        @variants [$name:ident $formatter:ident $($header:tt)*] [$($variant:tt)*] [$($display:tt)*] [$($source:tt)*] [$($from:tt)*]
+        $(#[$attribute:meta])* $new:ident(from $binding:ident: $type:ty $(,)?) => $message:literal $($rest:tt)*
+    ) => {
+        $crate::error_enum! {
+            @variants [$name $formatter $($header)*]
+            [$($variant)* $(#[$attribute])* $new($type),]
+            [$($display)* Self::$new($binding) => ::core::write!($formatter, $message),]
+            [$($source)* Self::$new($binding) => ::core::option::Option::Some($binding),]
+            [
+                $($from)*
+
+                impl ::core::convert::From<$type> for $name {
+                    fn from($binding: $type) -> Self {
+                        Self::$new($binding)
+                    }
+                }
+            ]
+            $($rest)*
+        }
+    };
+    (
+        @variants [$name:ident $formatter:ident $($header:tt)*] [$($variant:tt)*] [$($display:tt)*] [$($source:tt)*] [$($from:tt)*]
+        $(#[$attribute:meta])* $new:ident($($binding:ident: $type:ty),* $(,)?) => $message:literal $($rest:tt)*
+    ) => {
+        $crate::error_enum! {
+            @variants [$name $formatter $($header)*]
+            [$($variant)* $(#[$attribute])* $new($($type),*),]
+            [$($display)* Self::$new($($binding),*) => ::core::write!($formatter, $message),]
+            [$($source)*]
+            [$($from)*]
+            $($rest)*
+        }
+    };
+    (
+        @variants [$name:ident $formatter:ident $($header:tt)*] [$($variant:tt)*] [$($display:tt)*] [$($source:tt)*] [$($from:tt)*]
+        $(#[$attribute:meta])* $new:ident { $($field:ident: $type:ty),* $(,)? } => $message:literal $($rest:tt)*
+    ) => {
+        $crate::error_enum! {
+            @variants [$name $formatter $($header)*]
+            [$($variant)* $(#[$attribute])* $new { $($field: $type),* },]
+            [$($display)* Self::$new { $($field),* } => ::core::write!($formatter, $message),]
+            [$($source)*]
+            [$($from)*]
+            $($rest)*
+        }
+    };
+    (
+        @declare [$name:ident $formatter:ident $($header:tt)*] [$($variant:tt)*] [$($display:tt)*] [$($source:tt)*] [$($from:tt)*]
+    ) => {
+        $($header)* {
+            $($variant)*
+        }
+
+        impl ::core::fmt::Display for $name {
+            #[allow(unused_variables)]
+            fn fmt(&self, $formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                match self {
+                    $($display)*
+                }
+            }
+        }
+
+        impl ::core::error::Error for $name {
+            #[allow(unreachable_patterns)]
+            fn source(&self) -> ::core::option::Option<&(dyn ::core::error::Error + 'static)> {
+                match self {
+                    $($source)*
+                    _ => ::core::option::Option::None,
+                }
+            }
+        }
+
+        $($from)*
+    };
+    ($(#[$attribute:meta])* $visibility:vis enum $name:ident { $($variants:tt)* }) => {
+        $crate::error_enum! {
+            @variants [$name formatter $(#[$attribute])* $visibility enum $name] [] [] [] []
+            $($variants)*
+        }
+    };
+}
+
+/// An error with a context.
+///
+/// [`Display`](core::fmt::Display) writes the context, or the context and the
+/// error with the alternate flag `{:#}`. The error is the
+/// [`source`](core::error::Error::source) of this error.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::Contextual,
+///     std::error::Error,
+/// };
+///
+/// let error = Contextual::new("while parsing", "x".parse::<i32>().unwrap_err());
+///
+/// assert_eq!(*error.context(), "while parsing");
+/// assert_eq!(error.to_string(), "while parsing");
+/// assert_eq!(
+///     format!("{error:#}"),
+///     "while parsing: invalid digit found in string"
+/// );
+/// assert!(error.source().is_some());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Contextual<E, C = &'static str> {
+    context: C,
+    error: E,
+}
+
+impl<E, C> Contextual<E, C> {
+    /// Creates a new instance.
+    pub const fn new(context: C, error: E) -> Self {
+        Self {
+            context,
+            error,
+        }
+    }
+
+    /// Returns the context.
+    pub const fn context(&self) -> &C {
+        &self.context
+    }
+
+    /// Returns the error.
+    pub const fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// Returns the error and discards the context.
+    pub fn into_error(self) -> E {
+        self.error
+    }
+}
+
+impl<E: core::fmt::Display, C: core::fmt::Display> core::fmt::Display for Contextual<E, C> {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        if formatter.alternate() {
+            write!(formatter, "{}: {}", self.context, self.error)
+        } else {
+            write!(formatter, "{}", self.context)
+        }
+    }
+}
+
+impl<E: core::error::Error + 'static, C: core::fmt::Display + Debug> core::error::Error
+    for Contextual<E, C>
+{
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Option::Some(&self.error)
+    }
+}
+
+/// Attaches a context to the error of a [`Result`].
+///
+/// The error is wrapped in a [`Contextual`] only if there is one. A message
+/// without arguments is kept as a `&'static str` and is not formatted. A
+/// message with arguments is formatted into a `String`, which requires the
+/// `alloc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::{
+///         Contextual,
+///         context,
+///     },
+///     std::num::ParseIntError,
+/// };
+///
+/// fn parse(text: &str) -> Result<i32, Contextual<ParseIntError>> {
+///     let value = context!(text.parse::<i32>(), "while parsing the value")?;
+///     Ok(value * 2)
+/// }
+///
+/// assert_eq!(parse("21").unwrap(), 42);
+/// assert_eq!(
+///     parse("x").unwrap_err().to_string(),
+///     "while parsing the value"
+/// );
+/// ```
+///
+/// ```
+/// # #[cfg(feature = "alloc")] {
+/// use keymacro::context;
+///
+/// let path = "config.toml";
+/// let error = context!("x".parse::<u8>(), "while opening {}", path).unwrap_err();
+/// assert_eq!(
+///     format!("{error:#}"),
+///     "while opening config.toml: invalid digit found in string"
+/// );
+/// # }
+/// ```
+#[macro_export]
+macro_rules! context {
+    ($result:expr, $context:literal $(,)?) => {
+        ::core::result::Result::map_err($result, |error| $crate::Contextual::new($context, error))
+    };
+    ($result:expr, $context:literal, $($argument:tt)*) => {
+        $crate::__require_alloc!("`context!` with arguments requires the `alloc` feature" {
+            ::core::result::Result::map_err($result, |error| {
+                $crate::Contextual::new($crate::__private::format!($context, $($argument)*), error)
+            })
+        })
+    };
+}
+
+/// Declares a struct with a typed builder.
+///
+/// The name of the builder follows `=>`. A field with `= default` is optional
+/// and starts as the default, and any other field is required. Each field has
+/// a setter of the same name, and a required field can be set only once. The
+/// `build` method is available only after all required fields are set, which
+/// is checked at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::builder;
+///
+/// builder! {
+///     #[derive(Debug, PartialEq)]
+///     pub struct Config => ConfigBuilder {
+///         /// The host name.
+///         pub host: String,
+///         pub port: u16 = 8080,
+///         pub user: &'static str,
+///         pub verbose: bool = false,
+///     }
+/// }
+///
+/// let config = Config::builder()
+///     .port(443)
+///     .user("admin")
+///     .host(String::from("example.com"))
+///     .build();
+///
+/// assert_eq!(
+///     config,
+///     Config {
+///         host: String::from("example.com"),
+///         port: 443,
+///         user: "admin",
+///         verbose: false,
+///     }
+/// );
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::builder;
+///
+/// builder! {
+///     pub struct Config => ConfigBuilder {
+///         pub host: String,
+///         pub port: u16 = 8080,
+///     }
+/// }
+///
+/// Config::builder().port(443).build();
+/// ```
+#[macro_export]
+macro_rules! builder {
+    (@fields $header:tt [$($field:tt)*] [$($required:tt)*] [$($optional:tt)*] $(,)?) => {
+        $crate::builder!(@declare $header [$($field)*] [$($required)*] [$($optional)*]);
+    };
+    (
+        @fields $header:tt [$($field:tt)*] [$($required:tt)*] [$($optional:tt)*]
+        $(#[$attribute:meta])* $visibility:vis $name:ident: $type:ty = $default:expr
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::builder! {
+            @fields $header
+            [$($field)* $(#[$attribute])* $visibility $name: $type,]
+            [$($required)*]
+            [$($optional)* [$name: $type = $default]]
+            $($($rest)*)?
+        }
+    };
+    (
+        @fields $header:tt [$($field:tt)*] [$($required:tt)*] [$($optional:tt)*]
+        $(#[$attribute:meta])* $visibility:vis $name:ident: $type:ty
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::builder! {
+            @fields $header
+            [$($field)* $(#[$attribute])* $visibility $name: $type,]
+            [$($required)* [$name: $type]]
+            [$($optional)*]
+            $($($rest)*)?
+        }
+    };
+    (
+        @declare [$(#[$attribute:meta])* [$visibility:vis] $name:ident $builder:ident]
+        [$($field:tt)*]
+        [$([$required:ident: $required_type:ty])*]
+        [$([$optional:ident: $optional_type:ty = $default:expr])*]
+    ) => {
+        $(#[$attribute])*
+        $visibility struct $name {
+            $($field)*
+        }
+
+        impl $name {
+            /// Returns a builder.
+            pub fn builder() -> $builder {
+                $builder::new()
+            }
+        }
+
+        #[allow(non_camel_case_types)]
+        #[must_use]
+        $visibility struct $builder<$($required = $crate::__private::Unset),*> {
+            $($required: $required,)*
+            $($optional: $optional_type,)*
+        }
+
+        impl $builder {
+            /// Creates a new instance.
+            pub fn new() -> Self {
+                Self {
+                    $($required: $crate::__private::Unset,)*
+                    $($optional: $default,)*
+                }
+            }
+        }
+
+        impl ::core::default::Default for $builder {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        #[allow(non_camel_case_types)]
+        impl<$($required),*> $builder<$($required),*> {
+            $(
+                #[doc = ::core::concat!("Sets `", ::core::stringify!($optional), "`.")]
+                pub fn $optional(mut self, value: $optional_type) -> Self {
+                    self.$optional = value;
+                    self
+                }
+            )*
+        }
+
+        $crate::builder!(@required [$builder] [$($optional),*] [] $([$required: $required_type])*);
+
+        impl $builder<$($crate::__private::Set<$required_type>),*> {
+            /// Builds the value.
+            pub fn build(self) -> $name {
+                $name {
+                    $($required: self.$required.0,)*
+                    $($optional: self.$optional,)*
+                }
+            }
+        }
+    };
+    (@required [$builder:ident] [$($optional:ident),*] [$($before:ident),*]) => {};
+    (
+        @required [$builder:ident] [$($optional:ident),*] [$($before:ident),*]
+        [$name:ident: $type:ty] $([$after:ident: $after_type:ty])*
+    ) => {
+        #[allow(non_camel_case_types)]
+        impl<$($before,)* $($after),*> $builder<$($before,)* $crate::__private::Unset, $($after),*> {
+            #[doc = ::core::concat!("Sets `", ::core::stringify!($name), "`.")]
+            pub fn $name(self, value: $type) -> $builder<$($before,)* $crate::__private::Set<$type>, $($after),*> {
+                $builder {
+                    $name: $crate::__private::Set(value),
+                    $($before: self.$before,)*
+                    $($after: self.$after,)*
+                    $($optional: self.$optional,)*
+                }
+            }
+        }
+
+        $crate::builder! {
+            @required [$builder] [$($optional),*] [$($before,)* $name] $([$after: $after_type])*
+        }
+    };
+    (
+        $(#[$attribute:meta])*
+        $visibility:vis struct $name:ident => $builder:ident { $($fields:tt)* }
+    ) => {
+        $crate::builder!(@fields [$(#[$attribute])* [$visibility] $name $builder] [] [] [] $($fields)*);
+    };
+}
+
+/// Implements accessors for fields.
+///
+/// Each field lists its accessors after `=>`, which are given the visibility
+/// of the field:
+///
+/// - `get` is `get_field(&self) -> &T`.
+/// - `copy` is `get_field(&self) -> T`, for [`Copy`] fields.
+/// - `mut` is `field_mut(&mut self) -> &mut T`.
+/// - `set` is `set_field(&mut self, value: T)`.
+///
+/// This requires the `paste` feature.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::getset;
+///
+/// pub struct Person {
+///     name: String,
+///     age: u32,
+/// }
+///
+/// getset! {
+///     impl Person {
+///         pub name: String => get, mut;
+///         pub(crate) age: u32 => copy, set;
+///     }
+/// }
+///
+/// let mut person = Person {
+///     name: String::from("Alice"),
+///     age: 30,
+/// };
+///
+/// person.name_mut().push_str(" Smith");
+/// person.set_age(31);
+///
+/// assert_eq!(person.get_name(), "Alice Smith");
+/// assert_eq!(person.get_age(), 31);
+/// ```
+#[cfg(feature = "paste")]
+#[macro_export]
+macro_rules! getset {
+    (@accessor $visibility:vis $field:ident: $type:ty, get) => {
+        $crate::paste! {
+            #[doc = ::core::concat!("Returns a reference to `", ::core::stringify!($field), "`.")]
+            $visibility fn [<get_ $field>](&self) -> &$type {
+                &self.$field
+            }
+        }
+    };
+    (@accessor $visibility:vis $field:ident: $type:ty, copy) => {
+        $crate::paste! {
+            #[doc = ::core::concat!("Returns `", ::core::stringify!($field), "`.")]
+            $visibility fn [<get_ $field>](&self) -> $type {
+                self.$field
+            }
+        }
+    };
+    (@accessor $visibility:vis $field:ident: $type:ty, mut) => {
+        $crate::paste! {
+            #[doc = ::core::concat!("Returns a mutable reference to `", ::core::stringify!($field), "`.")]
+            $visibility fn [<$field _mut>](&mut self) -> &mut $type {
+                &mut self.$field
+            }
+        }
+    };
+    (@accessor $visibility:vis $field:ident: $type:ty, set) => {
+        $crate::paste! {
+            #[doc = ::core::concat!("Sets `", ::core::stringify!($field), "`.")]
+            $visibility fn [<set_ $field>](&mut self, value: $type) {
+                self.$field = value;
+            }
+        }
+    };
+    (@accessor $visibility:vis $field:ident: $type:ty, $option:tt) => {
+        ::core::compile_error!(::core::concat!("unknown accessor `", ::core::stringify!($option), "`"));
+    };
+    (
+        impl $(<$($generic:ident),* $(,)?>)? $type:ty {
+            $($visibility:vis $field:ident: $field_type:ty => $($option:tt),+ $(,)?;)*
+        }
+    ) => {
+        impl $(<$($generic),*>)? $type {
+            $($($crate::getset!(@accessor $visibility $field: $field_type, $option);)+)*
+        }
+    };
+}
+
+/// Implements methods that forward to a target.
+///
+/// It is used in an `impl` block. Each `to target { ... }` lists method
+/// signatures, and each method calls the method of the same name on the
+/// target with the same arguments. The receiver can be `&self`, `&mut self`
+/// or `self`.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::delegate;
+///
+/// struct Stack<T> {
+///     items: Vec<T>,
+/// }
+///
+/// impl<T> Stack<T> {
+///     delegate! {
+///         to self.items {
+///             /// Returns the number of the items.
+///             pub fn len(&self) -> usize;
+///             pub fn push(&mut self, item: T);
+///             pub fn pop(&mut self) -> Option<T>;
+///             pub fn truncate(&mut self, len: usize);
+///         }
+///     }
+/// }
+///
+/// let mut stack = Stack {
+///     items: Vec::new(),
+/// };
+/// stack.push(1);
+/// stack.push(2);
+/// stack.push(3);
+/// stack.truncate(2);
+///
+/// assert_eq!(stack.pop(), Some(2));
+/// assert_eq!(stack.len(), 1);
+/// ```
+#[macro_export]
+macro_rules! delegate {
+    (@methods [$($target:tt)+]) => {};
+    (
+        @methods [$($target:tt)+]
+        $(#[$attribute:meta])*
+        $visibility:vis fn $name:ident(&mut $self:ident $(, $argument:ident: $type:ty)*) $(-> $output:ty)?;
+        $($rest:tt)*
+    ) => {
+        $(#[$attribute])*
+        $visibility fn $name(&mut $self $(, $argument: $type)*) $(-> $output)? {
+            $($target)+.$name($($argument),*)
+        }
+
+        $crate::delegate!(@methods [$($target)+] $($rest)*);
+    };
+    (
+        @methods [$($target:tt)+]
+        $(#[$attribute:meta])*
+        $visibility:vis fn $name:ident(&$self:ident $(, $argument:ident: $type:ty)*) $(-> $output:ty)?;
+        $($rest:tt)*
+    ) => {
+        $(#[$attribute])*
+        $visibility fn $name(&$self $(, $argument: $type)*) $(-> $output)? {
+            $($target)+.$name($($argument),*)
+        }
+
+        $crate::delegate!(@methods [$($target)+] $($rest)*);
+    };
+    (
+        @methods [$($target:tt)+]
+        $(#[$attribute:meta])*
+        $visibility:vis fn $name:ident($self:ident $(, $argument:ident: $type:ty)*) $(-> $output:ty)?;
+        $($rest:tt)*
+    ) => {
+        $(#[$attribute])*
+        $visibility fn $name($self $(, $argument: $type)*) $(-> $output)? {
+            $($target)+.$name($($argument),*)
+        }
+
+        $crate::delegate!(@methods [$($target)+] $($rest)*);
+    };
+    (@target [$($target:tt)+] { $($methods:tt)* } $($rest:tt)*) => {
+        $crate::delegate!(@methods [$($target)+] $($methods)*);
+        $crate::delegate!($($rest)*);
+    };
+    (@target [$($target:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::delegate!(@target [$($target)* $token] $($rest)*);
+    };
+    () => {};
+    (to $($rest:tt)+) => {
+        $crate::delegate!(@target [] $($rest)+);
+    };
+}
+
+/// Declares traits that alias lists of bounds.
+///
+/// Each alias is a trait with the bounds as supertraits, implemented for every
+/// type that satisfies them, so it can be used in bounds.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::trait_alias;
+///
+/// trait_alias! {
+///     /// A shareable handler.
+///     pub trait Handler = Fn(u32) -> u32 + Send + Sync + 'static;
+///     pub trait Pairs<K, V> = Iterator<Item = (K, V)>;
+/// }
+///
+/// fn apply(handler: impl Handler, value: u32) -> u32 {
+///     handler(value)
+/// }
+///
+/// fn keys<K, V>(pairs: impl Pairs<K, V>) -> Vec<K> {
+///     pairs.map(|(key, _)| key).collect()
+/// }
+///
+/// assert_eq!(apply(|value| value * 2, 21), 42);
+/// assert_eq!(keys([(1, 'a'), (2, 'b')].into_iter()), [1, 2]);
+/// ```
+#[macro_export]
+macro_rules! trait_alias {
+    (@bounds [$($header:tt)*] [$($bound:tt)*] ; $($rest:tt)*) => {
+        $crate::trait_alias!(@declare $($header)* [$($bound)*]);
+        $crate::trait_alias!($($rest)*);
+    };
+    (@bounds [$($header:tt)*] [$($bound:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::trait_alias!(@bounds [$($header)*] [$($bound)* $token] $($rest)*);
+    };
+    (
+        @declare
+        [$(#[$attribute:meta])* $visibility:vis $name:ident $(<$($generic:ident),*>)?]
+        [$($bound:tt)+]
+    ) => {
+        $(#[$attribute])*
+        $visibility trait $name $(<$($generic),*>)?: $($bound)+ {}
+
+        impl<$($($generic,)*)? __Implementor: ?::core::marker::Sized + $($bound)+> $name $(<$($generic),*>)?
+            for __Implementor
+        {
+        }
+    };
+    () => {};
+    (
+        $(#[$attribute:meta])*
+        $visibility:vis trait $name:ident $(<$($generic:ident),* $(,)?>)? = $($rest:tt)+
+    ) => {
+        $crate::trait_alias! {
+            @bounds [[$(#[$attribute])* $visibility $name $(<$($generic),*>)?]] [] $($rest)+
+        }
+    };
+}
+
+/// Declares a sealed trait, which can be implemented only for listed types.
+///
+/// The first supertrait names a private module and a trait in it, which are
+/// declared and implemented for the types after `for`. The trait itself still
+/// has to be implemented for each type.
+///
+/// # Examples
+///
+/// ```
+/// mod shape {
+///     use keymacro::sealed;
+///
+///     #[derive(Clone, Copy)]
+///     pub struct Circle;
+///
+///     #[derive(Clone, Copy)]
+///     pub struct Square;
+///
+///     sealed! {
+///         /// A shape of this module.
+///         pub trait Shape: private::Sealed + Copy {
+///             fn corners(&self) -> u32;
+///         }
+///         for Circle, Square;
+///     }
+///
+///     impl Shape for Circle {
+///         fn corners(&self) -> u32 {
+///             0
+///         }
+///     }
+///
+///     impl Shape for Square {
+///         fn corners(&self) -> u32 {
+///             4
+///         }
+///     }
+/// }
+///
+/// use shape::Shape;
+///
+/// assert_eq!(shape::Square.corners(), 4);
+/// assert_eq!(shape::Circle.corners(), 0);
+/// ```
+///
+/// ```compile_fail
+/// mod shape {
+///     keymacro::sealed! {
+///         pub trait Shape: private::Sealed {}
+///         for u8;
+///     }
+/// }
+///
+/// struct Triangle;
+///
+/// impl shape::Shape for Triangle {}
+/// ```
+#[macro_export]
+macro_rules! sealed {
+    (
+        $(#[$attribute:meta])*
+        $visibility:vis trait $name:ident: $module:ident::$sealed:ident $(+ $bound:path)* {
+            $($item:tt)*
+        }
+        for $($type:ty),+ $(,)?;
+    ) => {
+        mod $module {
+            pub trait $sealed {}
+        }
+
+        $(impl $module::$sealed for $type {})+
+
+        $(#[$attribute])*
+        $visibility trait $name: $module::$sealed $(+ $bound)* {
+            $($item)*
+        }
+    };
+}
+
+/// Declares types with a single global instance each.
+///
+/// The instance is initialized on the first call to `instance`, which returns
+/// a reference to it. Under the `std` feature, concurrent first calls block on
+/// a [`LazyLock`](std::sync::LazyLock). Otherwise they spin on a [`Lazy`].
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::singleton,
+///     std::sync::atomic::{
+///         AtomicUsize,
+///         Ordering,
+///     },
+/// };
+///
+/// pub struct Database {
+///     url: &'static str,
+///     queries: AtomicUsize,
+/// }
+///
+/// impl Database {
+///     fn connect_default() -> Self {
+///         Self {
+///             url: "localhost",
+///             queries: AtomicUsize::new(0),
+///         }
+///     }
+/// }
+///
+/// singleton! {
+///     /// The database.
+///     pub DB: Database = Database::connect_default();
+/// }
+///
+/// AtomicUsize::fetch_add(&DB::instance().queries, 1, Ordering::Relaxed);
+/// AtomicUsize::fetch_add(&DB::instance().queries, 1, Ordering::Relaxed);
+///
+/// assert_eq!(DB::instance().url, "localhost");
+/// assert_eq!(
+///     AtomicUsize::load(&DB::instance().queries, Ordering::Relaxed),
+///     2
+/// );
+/// ```
+#[macro_export]
+macro_rules! singleton {
+    ($($(#[$attribute:meta])* $visibility:vis $name:ident: $type:ty = $value:expr;)*) => {
+        $(
+            $(#[$attribute])*
+            #[allow(non_camel_case_types)]
+            $visibility struct $name;
+
+            impl $name {
+                /// Returns the instance, initializing it on the first call.
+                $visibility fn instance() -> &'static $type {
+                    static INSTANCE: $crate::__private::SingletonCell<$type> =
+                        $crate::__private::SingletonCell::new(|| $value);
+
+                    $crate::__private::SingletonCell::force(&INSTANCE)
+                }
+            }
+        )*
+    };
+}
+
+/// A counter of calls at a code location, created by [`count_calls!`].
+///
+/// A counter is registered on its first hit, and [`CallCounter::all`] iterates
+/// over the registered counters.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::CallCounter;
+///
+/// static COUNTER: CallCounter = CallCounter::new("example", file!(), line!());
+///
+/// CallCounter::hit(&COUNTER);
+/// CallCounter::hit(&COUNTER);
+///
+/// assert_eq!(COUNTER.count(), 2);
+/// assert!(CallCounter::all().any(|counter| counter.name() == "example"));
+/// ```
+pub struct CallCounter {
+    name: &'static str,
+    file: &'static str,
+    line: u32,
+    count: AtomicUsize,
+    registered: AtomicBool,
+    next: AtomicPtr<CallCounter>,
+}
+
+static CALL_COUNTERS: AtomicPtr<CallCounter> = AtomicPtr::new(core::ptr::null_mut());
+
+impl CallCounter {
+    /// Creates a new instance.
+    pub const fn new(name: &'static str, file: &'static str, line: u32) -> Self {
+        Self {
+            name,
+            file,
+            line,
+            count: AtomicUsize::new(0),
+            registered: AtomicBool::new(false),
+            next: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+
+    /// Increments the count and registers the counter on the first hit.
+    pub fn hit(counter: &'static Self) {
+        AtomicUsize::fetch_add(&counter.count, 1, Ordering::Relaxed);
+
+        if AtomicBool::swap(&counter.registered, true, Ordering::Relaxed) {
+            return;
+        }
+
+        let pointer = counter as *const Self as *mut Self;
+        let mut head = AtomicPtr::load(&CALL_COUNTERS, Ordering::Relaxed);
+
+        loop {
+            AtomicPtr::store(&counter.next, head, Ordering::Relaxed);
+
+            match AtomicPtr::compare_exchange_weak(
+                &CALL_COUNTERS,
+                head,
+                pointer,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Result::Ok(_) => break,
+                Result::Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Returns an iterator over the registered counters, the latest first.
+    pub fn all() -> impl Iterator<Item = &'static Self> {
+        let mut pointer = AtomicPtr::load(&CALL_COUNTERS, Ordering::Acquire);
+
+        core::iter::from_fn(move || {
+            // SAFETY: Only references to static counters are registered.
+            let counter = unsafe { pointer.as_ref::<'static>() }?;
+            pointer = AtomicPtr::load(&counter.next, Ordering::Relaxed);
+            Option::Some(counter)
+        })
+    }
+
+    /// Writes the registered counters to the standard error.
+    ///
+    /// This requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn dump() {
+        for counter in Self::all() {
+            std::eprintln!("{counter:?}");
+        }
+    }
+
+    /// Returns the name.
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the file of the location.
+    pub const fn file(&self) -> &'static str {
+        self.file
+    }
+
+    /// Returns the line of the location.
+    pub const fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Returns the count.
+    pub fn count(&self) -> usize {
+        AtomicUsize::load(&self.count, Ordering::Relaxed)
+    }
+}
+
+impl Debug for CallCounter {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{} ({}:{}): {}",
+            self.name,
+            self.file,
+            self.line,
+            self.count()
+        )
+    }
+}
+
+/// Counts the calls at the code location.
+///
+/// Each use has its own static [`CallCounter`] with the name.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::{
+///     CallCounter,
+///     count_calls,
+/// };
+///
+/// fn checkout() {
+///     count_calls!("checkout_path");
+/// }
+///
+/// for _ in 0..3 {
+///     checkout();
+/// }
+///
+/// let counter = CallCounter::all()
+///     .find(|counter| counter.name() == "checkout_path")
+///     .unwrap();
+///
+/// assert_eq!(counter.count(), 3);
+/// ```
+#[macro_export]
+macro_rules! count_calls {
+    ($name:expr $(,)?) => {{
+        static COUNTER: $crate::CallCounter =
+            $crate::CallCounter::new($name, ::core::file!(), ::core::line!());
+
+        $crate::CallCounter::hit(&COUNTER);
+    }};
+}
+
+/// Logs a message when the scope exits.
+///
+/// The level is one of `trace`, `debug`, `info`, `warn` and `error`, and the
+/// message is formatted like [`log::log!`] when the scope exits, including
+/// early returns and unwinding. With `unwound`, which requires the `std`
+/// feature, ` (unwound)` is appended if the thread is panicking.
+///
+/// This requires the `log` feature.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::log_defer;
+///
+/// fn handle(id: u32) -> Result<(), ()> {
+///     log_defer!(info, "request {} finished", id);
+///     log_defer!(warn, unwound, "leaving request {id}");
+///
+///     if id == 0 {
+///         return Err(());
+///     }
+///
+///     Ok(())
+/// }
+///
+/// assert!(handle(0).is_err());
+/// assert!(handle(1).is_ok());
+/// ```
+#[cfg(feature = "log")]
+#[macro_export]
+macro_rules! log_defer {
+    (@level trace) => {
+        $crate::__private::log::Level::Trace
+    };
+    (@level debug) => {
+        $crate::__private::log::Level::Debug
+    };
+    (@level info) => {
+        $crate::__private::log::Level::Info
+    };
+    (@level warn) => {
+        $crate::__private::log::Level::Warn
+    };
+    (@level error) => {
+        $crate::__private::log::Level::Error
+    };
+    ($level:ident, unwound, $format:literal $($argument:tt)*) => {
+        $crate::defer! {
+            if $crate::__private::panicking() {
+                $crate::__private::log::log!(
+                    $crate::log_defer!(@level $level),
+                    "{} (unwound)",
+                    ::core::format_args!($format $($argument)*)
+                );
+            } else {
+                $crate::__private::log::log!($crate::log_defer!(@level $level), $format $($argument)*);
+            }
+        }
+    };
+    ($level:ident, $format:literal $($argument:tt)*) => {
+        $crate::defer! {
+            $crate::__private::log::log!($crate::log_defer!(@level $level), $format $($argument)*);
+        }
+    };
+}
+
+/// Enters a [`tracing`] span until the scope exits.
+///
+/// The arguments are a name and fields like [`tracing::span!`], optionally
+/// following a level, which is one of `trace`, `debug`, `info`, `warn` and
+/// `error` and defaults to `info`. The span is exited when the scope exits,
+/// including early returns and unwinding.
+///
+/// This requires the `tracing` feature.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::traced_scope;
+///
+/// fn handle(id: u32) -> u32 {
+///     traced_scope!("handle", id, kind = "request");
+///
+///     {
+///         traced_scope!(debug, "inner");
+///     }
+///
+///     id * 2
+/// }
+///
+/// assert_eq!(handle(21), 42);
+/// ```
+#[cfg(feature = "tracing")]
+#[macro_export]
+macro_rules! traced_scope {
+    (@level trace) => {
+        $crate::__private::tracing::Level::TRACE
+    };
+    (@level debug) => {
+        $crate::__private::tracing::Level::DEBUG
+    };
+    (@level info) => {
+        $crate::__private::tracing::Level::INFO
+    };
+    (@level warn) => {
+        $crate::__private::tracing::Level::WARN
+    };
+    (@level error) => {
+        $crate::__private::tracing::Level::ERROR
+    };
+    ($level:ident, $name:literal $($field:tt)*) => {
+        $crate::keep!($crate::__private::tracing::Span::entered(
+            $crate::__private::tracing::span!($crate::traced_scope!(@level $level), $name $($field)*)
+        ));
+    };
+    ($name:literal $($field:tt)*) => {
+        $crate::traced_scope!(info, $name $($field)*);
+    };
+}
+
+/// Emits a [`tracing`] event when the scope exits.
+///
+/// The arguments are a level, which is one of `trace`, `debug`, `info`, `warn`
+/// and `error`, and then fields and a message like [`tracing::event!`]. They
+/// are evaluated when the scope exits, including early returns and unwinding.
+///
+/// This requires the `tracing` feature.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::defer_event;
+///
+/// fn handle(id: u32) -> Result<(), ()> {
+///     defer_event!(info, id, "request finished");
+///     defer_event!(debug, "leaving request {id}");
+///
+///     if id == 0 {
+///         return Err(());
+///     }
+///
+///     Ok(())
+/// }
+///
+/// assert!(handle(0).is_err());
+/// assert!(handle(1).is_ok());
+/// ```
+#[cfg(feature = "tracing")]
+#[macro_export]
+macro_rules! defer_event {
+    ($level:ident, $($argument:tt)+) => {
+        $crate::defer! {
+            $crate::__private::tracing::event!($crate::traced_scope!(@level $level), $($argument)+);
+        }
+    };
+}
+
+/// Evaluates a block to a [`Result`] and passes it to a hook by reference.
+///
+/// The block runs in a closure, so `?` and `return` in it exit only the block.
+/// `on_ok` is called with a reference to the value, and `on_err` with a
+/// reference to the error, and then the result is returned as is. Either hook
+/// can be omitted.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::wrap_result,
+///     std::{
+///         cell::RefCell,
+///         num::ParseIntError,
+///     },
+/// };
+///
+/// let log = RefCell::new(Vec::new());
+///
+/// let parse = |text: &str| -> Result<i32, ParseIntError> {
+///     wrap_result!(
+///         {
+///             let value = text.parse::<i32>()?;
+///             Ok(value * 2)
+///         },
+///         on_ok = |value| RefCell::borrow_mut(&log).push(format!("ok {value}")),
+///         on_err = |error| RefCell::borrow_mut(&log).push(format!("error {error}")),
+///     )
+/// };
+///
+/// assert_eq!(parse("21"), Ok(42));
+/// assert!(parse("x").is_err());
+/// assert_eq!(
+///     *RefCell::borrow(&log),
+///     ["ok 42", "error invalid digit found in string"]
+/// );
+///
+/// let result: Result<(), &str> = wrap_result!({ Err("failed") }, on_ok = |_| unreachable!());
+/// assert_eq!(result, Err("failed"));
+/// ```
+#[macro_export]
+macro_rules! wrap_result {
+    ($block:block $(, on_ok = $ok:expr)? $(, on_err = $error:expr)? $(,)?) => {
+        $crate::__private::wrap_result(
+            (|| $block)(),
+            $crate::wrap_result!(@hook $($ok)?),
+            $crate::wrap_result!(@hook $($error)?),
+        )
+    };
+    (@hook) => {
+        |_| {}
+    };
+    (@hook $hook:expr) => {
+        $hook
+    };
+}
+
+/// Caches the value of an expression by a key.
+///
+/// Each use has its own cache, which is shared by all threads. The key is
+/// evaluated first, and the value is evaluated only if the key is not cached
+/// yet. The key must implement [`Eq`], [`Hash`](core::hash::Hash) and
+/// [`Send`], and the value must implement [`Clone`] and [`Send`], and both
+/// must be `'static`. The lock is not held while the value is evaluated, so
+/// the expression can use the same `memoize!` recursively.
+///
+/// This requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::memoize;
+///
+/// fn fibonacci(n: u64) -> u64 {
+///     memoize!(n => if n < 2 { n } else { fibonacci(n - 1) + fibonacci(n - 2) })
+/// }
+///
+/// fn area(width: u32, height: u32) -> u32 {
+///     memoize!((width, height) => width * height)
+/// }
+///
+/// assert_eq!(fibonacci(90), 2880067194370816120);
+/// assert_eq!(area(3, 4), 12);
+/// ```
+///
+/// In a generic function, all instantiations share the cache, and only the
+/// types of the key and the value keep their entries apart. If the value
+/// depends on another type parameter, put the type into the key.
+///
+/// ```
+/// use {
+///     keymacro::memoize,
+///     std::any::TypeId,
+/// };
+///
+/// fn size<T: 'static>() -> usize {
+///     memoize!(TypeId::of::<T>() => size_of::<T>())
+/// }
+///
+/// assert_eq!(size::<u8>(), 1);
+/// assert_eq!(size::<u64>(), 8);
+/// assert_eq!(size::<u8>(), 1);
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! memoize {
+    ($key:expr => $value:expr) => {{
+        static CACHE: $crate::__private::MemoCache =
+            $crate::__private::MemoCache::new(::core::option::Option::None);
+        $crate::__private::memoize(&CACHE, $key, || $value)
+    }};
+}
+
+/// Unrolls a `for` loop over a literal range at compile time.
+///
+/// The body is repeated once per index, and every use of the index is replaced by
+/// the integer literal, so it can be used where a constant is needed. Fields,
+/// methods, macros, path segments and field names of the same name are kept.
+/// The body must not bind another variable of the same name. The bounds must be
+/// integer literals, and `..=` includes the end. Each copy of the body is a
+/// separate block, so `break` and `continue` refer to an enclosing loop.
+///
+/// This requires the `unroll` feature.
+///
+/// # Examples
+///
+/// ```
+/// use keymacro::unroll;
+///
+/// let bytes = [1u8, 2, 3, 4, 5, 6, 7, 8];
+/// let mut sum = 0u32;
+///
+/// unroll!(
+///     for i in 0..8 {
+///         sum += u32::from(bytes[i]) << (i % 4 * 8);
+///     }
+/// );
+///
+/// assert_eq!(sum, 0x0C0A0806);
+///
+/// let mut squares = [0; 4];
+///
+/// unroll!(
+///     for i in 0..=3 {
+///         const SQUARE: usize = i * i;
+///         squares[i] = SQUARE;
+///     }
+/// );
+///
+/// assert_eq!(squares, [0, 1, 4, 9]);
+///
+/// struct Scale {
+///     i: usize,
+/// }
+///
+/// impl Scale {
+///     fn i(&self, value: usize) -> usize {
+///         self.i * value
+///     }
+/// }
+///
+/// let scale = Scale {
+///     i: 10,
+/// };
+/// let mut scaled = Vec::new();
+///
+/// unroll!(
+///     for i in 0..3 {
+///         scaled.push(scale.i + scale.i(i));
+///     }
+/// );
+///
+/// assert_eq!(scaled, [10, 20, 30]);
+/// ```
+///
+/// ```compile_fail
+/// use keymacro::unroll;
+///
+/// const COUNT: usize = 8;
+///
+/// unroll!(for i in 0..COUNT {});
+/// ```
+#[cfg(feature = "unroll")]
+pub use keymacro_macros::unroll;
+
+/// Re-exports the macros and types.
+///
+/// # Examples
+///
+/// ```
+/// use {
+///     keymacro::prelude::*,
+///     std::cell::Cell,
+/// };
+///
+/// let count = Cell::new(0);
+///
+/// {
+///     let guard = Defer::new(|| Cell::set(&count, Cell::get(&count) + 1));
+///     defer!(Cell::set(&count, Cell::get(&count) + 10));
+///     Defer::cancel(guard);
+/// }
+///
+/// assert_eq!(Cell::get(&count), 10);
+/// assert_eq!(text!("a" "b"), "a\nb");
+/// ```
+pub mod prelude {
+    #[cfg(feature = "attributes")]
+    pub use crate::guarded;
+    #[cfg(feature = "ip")]
+    pub use crate::ip;
+    #[cfg(feature = "log")]
+    pub use crate::log_defer;
+    #[cfg(feature = "todo_by")]
+    pub use crate::todo_by;
+    #[cfg(feature = "unroll")]
+    pub use crate::unroll;
+    pub use crate::{
+        AbortGuard,
+        CallCounter,
+        Contextual,
+        Defer,
+        DeferFn,
+        DropBomb,
+        Global,
+        Guard,
+        Lazy,
+        Version,
+        abort_on_panic,
+        array_init,
+        ascii,
+        assert_matches,
+        bail,
+        base64,
+        bits,
+        block,
+        bomb,
+        break_if,
+        btext,
+        builder,
+        bytesize,
+        capture,
+        cascade,
+        cfg_defer,
+        cfg_match,
+        clone,
+        cold,
+        compile_warn,
+        compose,
+        concat_bytes,
+        cond,
+        const_for,
+        context,
+        continue_if,
+        count_calls,
+        cstr,
+        debug_defer,
+        dedent,
+        default,
+        defer,
+        delegate,
+        display_from_text,
+        do_while,
+        doc_text,
+        duration,
+        ensure,
+        enum_from,
+        enum_variants,
+        env_text,
+        errdefer,
+        error_enum,
+        extract,
+        flags,
+        fn_guard,
+        for_else,
+        ftext,
+        global,
+        guard,
+        hex,
+        impl_ops,
+        include_text,
+        json_text,
+        keep,
+        keep_pinned,
+        lazy,
+        leave,
+        let_assert,
+        let_chain,
+        likely,
+        lines,
+        lower,
+        max,
+        min,
+        narrow,
+        newtype,
+        ok_or_return,
+        once,
+        pad_text,
+        path_text,
+        pin_local,
+        pipe,
+        platform_text,
+        product,
+        repeat_n,
+        repeat_text,
+        replace,
+        retry,
+        return_if,
+        return_if_let,
+        saturating,
+        sealed,
+        semver,
+        singleton,
+        sql_text,
+        static_assert,
+        sum,
+        swap_scoped,
+        switch,
+        table,
+        take,
+        tap,
+        text,
+        trait_alias,
+        tri,
+        try_finally,
+        try_scope,
+        unless,
+        unlikely,
+        unreachable_checked,
+        until,
+        unwrap_or_return,
+        upper,
+        utf16,
+        with,
+        with_cleanup,
+        within,
+        wrap_result,
+        wrapping,
+    };
+    #[cfg(feature = "async")]
+    pub use crate::{
+        AsyncDefer,
+        async_defer,
+    };
+    #[cfg(feature = "std")]
+    pub use crate::{
+        DeferOnSuccess,
+        DeferOnUnwind,
+        ThreadStatic,
+        catch,
+        defer_on_success,
+        defer_on_unwind,
+        memoize,
+        thread_static,
+        timeit,
+    };
+    #[cfg(feature = "alloc")]
+    pub use crate::{
+        DeferStack,
+        defer_first,
+        leak,
+        map,
+        set,
+    };
+    #[cfg(feature = "tracing")]
+    pub use crate::{
+        defer_event,
+        traced_scope,
+    };
+    #[cfg(feature = "paste")]
+    pub use crate::{
+        getset,
+        paste,
+    };
+}
+
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __require_alloc {
+    ($message:literal { $($token:tt)* }) => {
+        $($token)*
+    };
+}
+
+#[cfg(not(feature = "alloc"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __require_alloc {
+    ($message:literal { $($token:tt)* }) => {
+        ::core::compile_error!($message)
+    };
+}
+
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __require_std {
+    ($message:literal { $($token:tt)* }) => {
+        $($token)*
+    };
+}
+
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __require_std {
+    ($message:literal { $($token:tt)* }) => {
+        ::core::compile_error!($message)
+    };
+}
+
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "alloc")]
+    pub use alloc::{
+        boxed::Box,
+        collections::{
+            BTreeMap,
+            BTreeSet,
+        },
+        format,
+    };
+    #[cfg(feature = "ip")]
+    use core::net::{
+        IpAddr,
+        Ipv4Addr,
+        Ipv6Addr,
+        SocketAddr,
+        SocketAddrV4,
+        SocketAddrV6,
+    };
+    pub use core::num::{
+        Saturating,
+        Wrapping,
+    };
+    #[cfg(feature = "log")]
+    pub use log;
+    #[cfg(feature = "std")]
+    pub use std::{
+        collections::{
+            HashMap,
+            HashSet,
+        },
+        panic::{
+            AssertUnwindSafe,
+            catch_unwind,
+        },
+        thread::panicking,
+        time::Instant,
+    };
+    #[cfg(feature = "tracing")]
+    pub use tracing;
+    use {
+        crate::{
+            Guard,
+            Version,
+        },
+        core::{
+            ffi::CStr,
+            fmt::{
+                self,
+                Arguments,
+                Debug,
+                Formatter,
+                LowerHex,
+            },
+            mem::MaybeUninit,
+            ops::{
+                BitAnd,
+                Not,
+            },
+            time::Duration,
+        },
+    };
+
+    pub struct Buffer<'a> {
+        bytes: &'a mut [u8],
+        len: usize,
+    }
+
+    impl<'a> Buffer<'a> {
+        pub const fn new(bytes: &'a mut [u8]) -> Self {
+            Self {
+                bytes,
+                len: 0,
+            }
+        }
+
+        pub const fn finish(self) -> usize {
+            self.len
+        }
+
+        pub const fn push(&mut self, byte: u8) {
+            if self.len < self.bytes.len() {
+                self.bytes[self.len] = byte;
+            }
+
+            self.len += 1;
+        }
+
+        pub const fn push_slice(&mut self, bytes: &[u8]) {
+            self.push_range(bytes, 0, bytes.len());
+        }
+
+        pub const fn push_range(&mut self, bytes: &[u8], start: usize, end: usize) {
+            let mut index = start;
+
+            while index < end {
+                self.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+
+    pub struct Bytes<T>(pub T);
+
+    impl Bytes<u8> {
+        pub const fn as_bytes(&self) -> &[u8] {
+            core::slice::from_ref(&self.0)
+        }
+    }
+
+    impl<const N: usize> Bytes<&[u8; N]> {
+        pub const fn as_bytes(&self) -> &[u8] {
+            self.0
+        }
+    }
+
+    pub struct Moved<T>(pub T);
+
+    impl<T> Moved<T> {
+        pub fn into_inner(moved: Self) -> T {
+            moved.0
+        }
+    }
+
+    pub const fn to_str(bytes: &[u8]) -> &str {
+        match core::str::from_utf8(bytes) {
+            Result::Ok(text) => text,
+            Result::Err(_) => panic!("invalid UTF-8"),
+        }
+    }
+
+    const fn is_indent(byte: u8) -> bool {
+        byte == b' ' || byte == b'\t'
+    }
+
+    const fn line_end(bytes: &[u8], start: usize) -> usize {
+        let mut index = start;
+
+        while index < bytes.len() && bytes[index] != b'\n' {
+            index += 1;
+        }
+
+        index
+    }
+
+    const fn indent_end(bytes: &[u8], start: usize, end: usize) -> usize {
+        let mut index = start;
+
+        while index < end && is_indent(bytes[index]) {
+            index += 1;
+        }
+
+        index
+    }
+
+    const fn is_blank(bytes: &[u8], start: usize, end: usize) -> bool {
+        let index = indent_end(bytes, start, end);
+        index == end || index + 1 == end && bytes[index] == b'\r'
+    }
+
+    pub const fn dedent(text: &str, output: &mut [u8]) -> usize {
+        let bytes = text.as_bytes();
+
+        let start = match bytes {
+            [b'\n', ..] => 1,
+            [b'\r', b'\n', ..] => 2,
+            _ => 0,
+        };
+
+        let mut indent = usize::MAX;
+        let mut index = start;
+
+        while index < bytes.len() {
+            let end = line_end(bytes, index);
+
+            if !is_blank(bytes, index, end) {
+                let width = indent_end(bytes, index, end) - index;
+
+                if width < indent {
+                    indent = width;
+                }
+            }
+
+            index = end + 1;
+        }
+
+        let mut buffer = Buffer::new(output);
+        let mut index = start;
+
+        while index < bytes.len() {
+            let end = line_end(bytes, index);
+
+            let content = if is_blank(bytes, index, end) {
+                indent_end(bytes, index, end)
+            } else {
+                index + indent
+            };
+
+            buffer.push_range(bytes, content, end);
+
+            if end < bytes.len() {
+                buffer.push(b'\n');
+            }
+
+            index = end + 1;
+        }
+
+        buffer.finish()
+    }
+
+    /// # Safety
+    ///
+    /// It must never be reached.
+    pub const unsafe fn unreachable_unchecked() -> ! {
+        // SAFETY: The caller guarantees that this is never reached.
+        unsafe { core::hint::unreachable_unchecked() }
+    }
+
+    pub const fn join(
+        lines: &[&[u8]],
+        prefix: &[u8],
+        separator: &[u8],
+        trailing: bool,
+        output: &mut [u8],
+    ) -> usize {
+        let mut buffer = Buffer::new(output);
+        let mut index = 0;
+
+        while index < lines.len() {
+            if index != 0 {
+                buffer.push_slice(separator);
+            }
+
+            buffer.push_slice(prefix);
+            buffer.push_slice(lines[index]);
+            index += 1;
+        }
+
+        if trailing && !lines.is_empty() {
+            buffer.push_slice(separator);
+        }
+
+        buffer.finish()
+    }
+
+    pub const fn to_c_str(text: &str) -> &CStr {
+        match CStr::from_bytes_with_nul(text.as_bytes()) {
+            Result::Ok(text) => text,
+            Result::Err(_) => panic!("interior NUL"),
+        }
+    }
+
+    pub const fn encode_utf16(text: &str, nul: bool, output: &mut [u16]) -> usize {
+        const fn push(output: &mut [u16], len: &mut usize, unit: u16) {
+            if *len < output.len() {
+                output[*len] = unit;
+            }
+
+            *len += 1;
+        }
+
+        let bytes = text.as_bytes();
+        let mut len = 0;
+        let mut index = 0;
+
+        while index < bytes.len() {
+            let byte = bytes[index] as u32;
+
+            let (code, width) = if byte < 0x80 {
+                (byte, 1)
+            } else if byte < 0xE0 {
+                ((byte & 0x1F) << 6 | bytes[index + 1] as u32 & 0x3F, 2)
+            } else if byte < 0xF0 {
+                (
+                    (byte & 0x0F) << 12
+                        | (bytes[index + 1] as u32 & 0x3F) << 6
+                        | bytes[index + 2] as u32 & 0x3F,
+                    3,
+                )
+            } else {
+                (
+                    (byte & 0x07) << 18
+                        | (bytes[index + 1] as u32 & 0x3F) << 12
+                        | (bytes[index + 2] as u32 & 0x3F) << 6
+                        | bytes[index + 3] as u32 & 0x3F,
+                    4,
+                )
+            };
+
+            if code < 0x10000 {
+                push(output, &mut len, code as u16);
+            } else {
+                let code = code - 0x10000;
+                push(output, &mut len, (0xD800 | code >> 10) as u16);
+                push(output, &mut len, (0xDC00 | code & 0x3FF) as u16);
+            }
+
+            index += width;
+        }
+
+        if nul {
+            push(output, &mut len, 0);
+        }
+
+        len
+    }
+
+    pub const fn assert_ascii(text: &str) -> &str {
+        assert!(text.is_ascii(), "non-ASCII text");
+        text
+    }
+
+    pub const fn decode_hex(text: &str, output: &mut [u8]) -> usize {
+        let bytes = text.as_bytes();
+        let mut buffer = Buffer::new(output);
+        let mut high = Option::None;
+        let mut index = 0;
+
+        while index < bytes.len() {
+            let byte = bytes[index];
+            index += 1;
+
+            let digit = match byte {
+                b'0'..=b'9' => byte - b'0',
+                b'a'..=b'f' => byte - b'a' + 10,
+                b'A'..=b'F' => byte - b'A' + 10,
+                _ if byte.is_ascii_whitespace() => continue,
+                _ => panic!("invalid hexadecimal digit"),
+            };
+
+            high = match high {
+                Option::Some(high) => {
+                    buffer.push(high << 4 | digit);
+                    Option::None
+                },
+                Option::None => Option::Some(digit),
+            };
+        }
+
+        assert!(high.is_none(), "odd number of hexadecimal digits");
+        buffer.finish()
+    }
+
+    pub const fn decode_base64(text: &str, output: &mut [u8]) -> usize {
+        let bytes = text.as_bytes();
+        let mut buffer = Buffer::new(output);
+        let mut bits = 0u32;
+        let mut len = 0;
+        let mut digits = 0;
+        let mut padded = false;
+        let mut index = 0;
+
+        while index < bytes.len() {
+            let byte = bytes[index];
+            index += 1;
+
+            let digit = match byte {
+                b'A'..=b'Z' => byte - b'A',
+                b'a'..=b'z' => byte - b'a' + 26,
+                b'0'..=b'9' => byte - b'0' + 52,
+                b'+' => 62,
+                b'/' => 63,
+                b'=' => {
+                    padded = true;
+                    continue;
+                },
+                _ if byte.is_ascii_whitespace() => continue,
+                _ => panic!("invalid base64 digit"),
+            };
+
+            assert!(!padded, "base64 digit after padding");
+            bits = bits << 6 | digit as u32;
+            len += 6;
+            digits += 1;
+
+            if len >= 8 {
+                len -= 8;
+                buffer.push((bits >> len) as u8);
+                bits &= (1 << len) - 1;
+            }
+        }
+
+        assert!(digits % 4 != 1, "invalid base64 length");
+        buffer.finish()
+    }
+
+    #[cold]
+    const fn cold() {}
+
+    #[inline(always)]
+    pub const fn likely(condition: bool) -> bool {
+        if !condition {
+            cold();
+        }
+
+        condition
+    }
+
+    #[inline(always)]
+    pub const fn unlikely(condition: bool) -> bool {
+        if condition {
+            cold();
+        }
+
+        condition
+    }
+
+    #[cold]
+    #[inline(never)]
+    pub fn cold_path<T, F: FnOnce() -> T>(path: F) -> T {
+        path()
+    }
+
+    pub fn tap<T, F: FnOnce(&T)>(value: T, inspect: F) -> T {
+        inspect(&value);
+        value
+    }
+
+    pub fn on_retry<E, F: FnOnce(usize, &E)>(attempt: usize, error: &E, on_retry: F) {
+        on_retry(attempt, error);
+    }
+
+    #[cfg(feature = "std")]
+    pub fn report_elapsed(label: &str, elapsed: Duration) {
+        std::eprintln!("{label}: {elapsed:?}");
+    }
+
+    #[cfg(feature = "std")]
+    pub fn sink_elapsed<L, F: FnOnce(L, Duration)>(label: L, elapsed: Duration, sink: F) {
+        sink(label, elapsed);
+    }
+
+    pub fn map_error<T, E, R, F: FnOnce(E) -> R>(result: Result<T, E>, map: F) -> Result<T, R> {
+        Result::map_err(result, map)
+    }
+
+    pub struct Described<'a, T>(pub &'a T);
+
+    pub trait DescribeDebug {
+        fn describe(&self) -> Option<&dyn Debug>;
+    }
+
+    impl<T: Debug> DescribeDebug for &Described<'_, T> {
+        fn describe(&self) -> Option<&dyn Debug> {
+            Option::Some(self.0)
+        }
+    }
+
+    pub trait DescribeOther {
+        fn describe(&self) -> Option<&dyn Debug>;
+    }
+
+    impl<T> DescribeOther for Described<'_, T> {
+        fn describe(&self) -> Option<&dyn Debug> {
+            Option::None
+        }
+    }
+
+    #[track_caller]
+    pub fn let_assert_failed(value: Option<&dyn Debug>, pattern: &str) -> ! {
+        match value {
+            Option::Some(value) => {
+                panic!("assertion failed: `{value:?}` does not match `{pattern}`")
+            },
+            Option::None => panic!("assertion failed: the value does not match `{pattern}`"),
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_matches_failed<T: Debug>(
+        value: &T,
+        pattern: &str,
+        message: Option<Arguments>,
+    ) -> ! {
+        match message {
+            Option::Some(message) => {
+                panic!(
+                    "assertion `left matches right` failed: {message}\n  left: {value:?}\n right: {pattern}"
+                )
+            },
+            Option::None => panic!(
+                "assertion `left matches right` failed\n  left: {value:?}\n right: {pattern}"
+            ),
+        }
+    }
+
+    pub fn min<T: PartialOrd>(left: T, right: T) -> T {
+        if right < left {
+            right
+        } else {
+            left
+        }
+    }
+
+    pub fn max<T: PartialOrd>(left: T, right: T) -> T {
+        if right < left {
+            left
+        } else {
+            right
+        }
+    }
+
+    pub fn array_init<T, const N: usize, F: FnMut(usize) -> T>(mut init: F) -> [T; N] {
+        let mut guard = Guard::new(
+            ([const { MaybeUninit::uninit() }; N], 0),
+            |(mut array, initialized): ([MaybeUninit<T>; N], usize)| {
+                for element in &mut array[..initialized] {
+                    // SAFETY: The first `initialized` elements are initialized.
+                    unsafe { MaybeUninit::assume_init_drop(element) };
+                }
+            },
+        );
+
+        for index in 0..N {
+            let (array, initialized) = &mut *guard;
+            array[index] = MaybeUninit::new(init(index));
+            *initialized += 1;
+        }
+
+        let (array, _) = Guard::into_inner(guard);
+
+        // SAFETY: All the elements are initialized.
+        unsafe { (&raw const array).cast::<[T; N]>().read() }
+    }
+
+    pub const fn duration(parts: &[(u64, u64)]) -> Duration {
+        let mut nanos: u128 = 0;
+        let mut index = 0;
+
+        while index < parts.len() {
+            let (value, unit) = parts[index];
+
+            nanos = match nanos.checked_add(value as u128 * unit as u128) {
+                Option::Some(nanos) => nanos,
+                Option::None => panic!("the duration overflows"),
+            };
+
+            index += 1;
+        }
+
+        let secs = nanos / 1_000_000_000;
+
+        if secs > u64::MAX as u128 {
+            panic!("the duration overflows");
+        }
+
+        Duration::new(secs as u64, (nanos % 1_000_000_000) as u32)
+    }
+
+    pub const fn bytesize(parts: &[(u64, u64)]) -> u128 {
+        let mut bytes: u128 = 0;
+        let mut index = 0;
+
+        while index < parts.len() {
+            let (value, unit) = parts[index];
+
+            bytes = match bytes.checked_add(value as u128 * unit as u128) {
+                Option::Some(bytes) => bytes,
+                Option::None => panic!("the byte count overflows"),
+            };
+
+            index += 1;
+        }
+
+        bytes
+    }
+
+    pub const fn parse_version(text: &'static str) -> Version {
+        let bytes = text.as_bytes();
+        let (major, index) = version_number(bytes, 0);
+        let index = version_dot(bytes, index);
+        let (minor, index) = version_number(bytes, index);
+        let index = version_dot(bytes, index);
+        let (patch, mut index) = version_number(bytes, index);
+        let mut pre = "";
+        let mut build = "";
+
+        if index < bytes.len() && bytes[index] == b'-' {
+            let end = version_identifiers(bytes, index + 1, true);
+            pre = version_part(bytes, index + 1, end);
+            index = end;
+        }
+
+        if index < bytes.len() && bytes[index] == b'+' {
+            let end = version_identifiers(bytes, index + 1, false);
+            build = version_part(bytes, index + 1, end);
+            index = end;
+        }
+
+        if index != bytes.len() {
+            panic!("invalid version");
+        }
+
+        Version {
+            major,
+            minor,
+            patch,
+            pre,
+            build,
+        }
+    }
+
+    const fn version_number(bytes: &[u8], start: usize) -> (u64, usize) {
+        let mut value: u64 = 0;
+        let mut index = start;
+
+        while index < bytes.len() && bytes[index].is_ascii_digit() {
+            value = match value.checked_mul(10) {
+                Option::Some(value) => match value.checked_add((bytes[index] - b'0') as u64) {
+                    Option::Some(value) => value,
+                    Option::None => panic!("the version number overflows"),
+                },
+                Option::None => panic!("the version number overflows"),
+            };
+
+            index += 1;
+        }
+
+        if index == start {
+            panic!("expected a version number");
+        }
+
+        if index - start > 1 && bytes[start] == b'0' {
+            panic!("the version number has a leading zero");
+        }
+
+        (value, index)
+    }
+
+    const fn version_dot(bytes: &[u8], index: usize) -> usize {
+        if index < bytes.len() && bytes[index] == b'.' {
+            index + 1
+        } else {
+            panic!("expected `.`")
+        }
+    }
+
+    const fn version_identifiers(bytes: &[u8], start: usize, pre: bool) -> usize {
+        let mut index = start;
+
+        loop {
+            let identifier = index;
+            let mut numeric = true;
+
+            while index < bytes.len()
+                && (bytes[index].is_ascii_alphanumeric() || bytes[index] == b'-')
+            {
+                numeric &= bytes[index].is_ascii_digit();
+                index += 1;
+            }
+
+            if index == identifier {
+                panic!("expected an identifier");
+            }
+
+            if pre && numeric && index - identifier > 1 && bytes[identifier] == b'0' {
+                panic!("the numeric identifier has a leading zero");
+            }
+
+            if index < bytes.len() && bytes[index] == b'.' {
+                index += 1;
+            } else {
+                return index;
+            }
+        }
+    }
+
+    const fn version_part(bytes: &'static [u8], start: usize, end: usize) -> &'static str {
+        let (_, part) = bytes.split_at(start);
+        let (part, _) = part.split_at(end - start);
+        to_str(part)
+    }
+
+    pub const fn normalize_lines(
+        text: &str,
+        prefix: &[u8],
+        separator: &[u8],
+        trailing: bool,
+        output: &mut [u8],
+    ) -> usize {
+        let bytes = text.as_bytes();
+        let mut buffer = Buffer::new(output);
+        let mut start = 0;
+
+        while start < bytes.len() {
+            let end = line_end(bytes, start);
+
+            let content_end = if end > start && bytes[end - 1] == b'\r' {
+                end - 1
+            } else {
+                end
+            };
+
+            if start != 0 {
+                buffer.push_slice(separator);
+            }
+
+            buffer.push_slice(prefix);
+            buffer.push_range(bytes, start, content_end);
+            start = end + 1;
+        }
+
+        if trailing && !bytes.is_empty() {
+            buffer.push_slice(separator);
+        }
+
+        buffer.finish()
+    }
+
+    #[track_caller]
+    pub fn narrow<T: Copy + Debug, U: TryFrom<T>>(
+        value: T,
+        checked: bool,
+        target: &str,
+        cast: impl FnOnce(T) -> U,
+    ) -> U {
+        if !checked {
+            return cast(value);
+        }
+
+        match U::try_from(value) {
+            Result::Ok(narrowed) => narrowed,
+            Result::Err(_) => panic!("`{value:?}` does not fit in `{target}`"),
+        }
+    }
+
+    pub fn is_negative<T: Default + PartialOrd>(value: &T) -> bool {
+        *value < T::default()
+    }
+
+    pub trait Word: Copy {
+        const BITS: u32;
+
+        fn extract(self, low: u32, width: u32) -> Self;
+
+        fn insert(&mut self, low: u32, width: u32, value: Self) -> bool;
+    }
+
+    macro_rules! word {
+        ($($type:ty),*) => {
+            $(
+                impl Word for $type {
+                    const BITS: u32 = <$type>::BITS;
+
+                    fn extract(self, low: u32, width: u32) -> Self {
+                        (self >> low) & (<$type>::MAX >> (<$type>::BITS - width))
+                    }
+
+                    fn insert(&mut self, low: u32, width: u32, value: Self) -> bool {
+                        let mask = <$type>::MAX >> (<$type>::BITS - width);
+
+                        if value & !mask != 0 {
+                            return false;
+                        }
+
+                        *self = (*self & !(mask << low)) | (value << low);
+                        true
+                    }
+                }
+            )*
+        };
+    }
+
+    word!(u8, u16, u32, u64, u128, usize);
+
+    pub fn extract_bits<T: Word, const HIGH: u32, const LOW: u32>(word: T) -> T {
+        const {
+            assert!(
+                LOW <= HIGH && HIGH < T::BITS,
+                "the bit range is out of bounds"
+            );
+        }
+
+        word.extract(LOW, HIGH - LOW + 1)
+    }
+
+    #[track_caller]
+    pub fn insert_bits<T: Word, const HIGH: u32, const LOW: u32>(word: &mut T, value: T) {
+        const {
+            assert!(
+                LOW <= HIGH && HIGH < T::BITS,
+                "the bit range is out of bounds"
+            );
+        }
+
+        if !word.insert(LOW, HIGH - LOW + 1, value) {
+            panic!("the value does not fit in bits {HIGH}:{LOW}");
+        }
+    }
+
+    pub fn debug_flags<T>(
+        formatter: &mut Formatter,
+        name: &str,
+        bits: T,
+        flags: &[(&str, T)],
+    ) -> fmt::Result
+    where
+        T: Copy + Default + PartialEq + BitAnd<Output = T> + Not<Output = T> + LowerHex,
+    {
+        write!(formatter, "{name}(")?;
+        let mut remaining = bits;
+        let mut first = true;
+
+        for &(flag, value) in flags {
+            if value != T::default() && bits & value == value {
+                if !first {
+                    formatter.write_str(" | ")?;
+                }
+
+                formatter.write_str(flag)?;
+                remaining = remaining & !value;
+                first = false;
+            }
+        }
+
+        if first {
+            write!(formatter, "{remaining:#x}")?;
+        } else if remaining != T::default() {
+            write!(formatter, " | {remaining:#x}")?;
+        }
+
+        formatter.write_str(")")
+    }
+
+    pub struct Unset;
+
+    pub struct Set<T>(pub T);
+
+    #[cfg(feature = "std")]
+    pub type SingletonCell<T> = std::sync::LazyLock<T>;
+
+    #[cfg(not(feature = "std"))]
+    pub type SingletonCell<T> = crate::Lazy<T>;
+
+    pub fn wrap_result<T, E>(
+        result: Result<T, E>,
+        on_ok: impl FnOnce(&T),
+        on_error: impl FnOnce(&E),
+    ) -> Result<T, E> {
+        match &result {
+            Result::Ok(value) => on_ok(value),
+            Result::Err(error) => on_error(error),
+        }
+
+        result
+    }
+
+    #[cfg(feature = "std")]
+    pub type MemoCache =
+        std::sync::Mutex<Option<HashMap<core::any::TypeId, Box<dyn core::any::Any + Send>>>>;
+
+    #[cfg(feature = "std")]
+    pub fn memoize<K, V>(cache: &MemoCache, key: K, value: impl FnOnce() -> V) -> V
+    where
+        K: Eq + core::hash::Hash + Send + 'static,
+        V: Clone + Send + 'static,
+    {
+        // A generic caller shares the cache between instantiations, so each pair
+        // of key and value types gets its own map.
+        let id = core::any::TypeId::of::<(K, V)>();
+
+        let lock = || match std::sync::Mutex::lock(cache) {
+            Result::Ok(cache) => cache,
+            Result::Err(error) => std::sync::PoisonError::into_inner(error),
+        };
+
+        if let Option::Some(map) = lock()
+            .as_ref()
+            .and_then(|maps| maps.get(&id))
+            .and_then(|map| map.downcast_ref::<HashMap<K, V>>())
+        {
+            if let Option::Some(value) = map.get(&key) {
+                return value.clone();
+            }
+        }
+
+        let value = value();
+
+        if let Option::Some(map) = lock()
+            .get_or_insert_with(HashMap::new)
+            .entry(id)
+            .or_insert_with(|| Box::new(HashMap::<K, V>::new()))
+            .downcast_mut::<HashMap<K, V>>()
+        {
+            map.insert(key, value.clone());
+        }
+
+        value
+    }
+
+    pub const fn repeat(text: &[u8], count: usize, output: &mut [u8]) -> usize {
+        let mut buffer = Buffer::new(output);
+        let mut index = 0;
+
+        while index < count {
+            buffer.push_slice(text);
+            index += 1;
+        }
+
+        buffer.finish()
+    }
+
+    pub enum Align {
+        Left,
+        Right,
+        Center,
+    }
+
+    const fn char_count(bytes: &[u8]) -> usize {
+        let mut count = 0;
+        let mut index = 0;
+
+        while index < bytes.len() {
+            if bytes[index] & 0xC0 != 0x80 {
+                count += 1;
+            }
+
+            index += 1;
+        }
+
+        count
+    }
+
+    pub const fn pad(
+        text: &[u8],
+        width: usize,
+        fill: char,
+        align: Align,
+        output: &mut [u8],
+    ) -> usize {
+        let count = char_count(text);
+
+        if count > width {
+            panic!("the text is longer than the width");
+        }
+
+        let padding = width - count;
+
+        let before = match align {
+            Align::Left => 0,
+            Align::Right => padding,
+            Align::Center => padding / 2,
+        };
+
+        let mut fill_bytes = [0; 4];
+        let fill = char::encode_utf8(fill, &mut fill_bytes).as_bytes();
+        let mut buffer = Buffer::new(output);
+        let mut index = 0;
+
+        while index < padding {
+            if index == before {
+                buffer.push_slice(text);
+            }
+
+            buffer.push_slice(fill);
+            index += 1;
+        }
+
+        if before == padding {
+            buffer.push_slice(text);
+        }
+
+        buffer.finish()
+    }
+
+    const fn column_width(rows: &[&[&[u8]]], column: usize) -> usize {
+        let mut width = 0;
+        let mut index = 0;
+
+        while index < rows.len() {
+            if column < rows[index].len() {
+                let count = char_count(rows[index][column]);
+
+                if count > width {
+                    width = count;
+                }
+            }
+
+            index += 1;
+        }
+
+        width
+    }
+
+    pub const fn table(rows: &[&[&[u8]]], separator: &[u8], output: &mut [u8]) -> usize {
+        let mut buffer = Buffer::new(output);
+        let mut row = 0;
+
+        while row < rows.len() {
+            if row != 0 {
+                buffer.push(b'\n');
+            }
+
+            let mut column = 0;
+
+            while column < rows[row].len() {
+                let cell = rows[row][column];
+                buffer.push_slice(cell);
+
+                if column + 1 < rows[row].len() {
+                    let mut padding = column_width(rows, column) - char_count(cell);
+
+                    while padding > 0 {
+                        buffer.push(b' ');
+                        padding -= 1;
+                    }
+
+                    buffer.push_slice(separator);
+                }
+
+                column += 1;
+            }
+
+            row += 1;
+        }
+
+        buffer.finish()
+    }
+
+    pub const fn ascii_case<const N: usize>(text: &[u8], upper: bool) -> [u8; N] {
+        let mut bytes = [0; N];
+        let mut index = 0;
+
+        while index < N {
+            bytes[index] = if upper {
+                text[index].to_ascii_uppercase()
+            } else {
+                text[index].to_ascii_lowercase()
+            };
+
+            index += 1;
+        }
+
+        bytes
+    }
+
+    const JSON_INVALID: usize = usize::MAX;
+
+    pub const fn is_json(bytes: &[u8]) -> bool {
+        let end = json_value(bytes, json_whitespace(bytes, 0));
+        end != JSON_INVALID && json_whitespace(bytes, end) == bytes.len()
+    }
+
+    const fn json_whitespace(bytes: &[u8], start: usize) -> usize {
+        let mut index = start;
+
+        while index < bytes.len() && matches!(bytes[index], b' ' | b'\t' | b'\n' | b'\r') {
+            index += 1;
+        }
+
+        index
+    }
+
+    const fn json_literal(bytes: &[u8], start: usize, literal: &[u8]) -> usize {
+        let mut index = 0;
+
+        while index < literal.len() {
+            if start + index >= bytes.len() || bytes[start + index] != literal[index] {
+                return JSON_INVALID;
+            }
+
+            index += 1;
+        }
+
+        start + literal.len()
+    }
+
+    const fn json_digits(bytes: &[u8], start: usize) -> usize {
+        let mut index = start;
+
+        while index < bytes.len() && bytes[index].is_ascii_digit() {
+            index += 1;
+        }
+
+        if index == start {
+            JSON_INVALID
+        } else {
+            index
+        }
+    }
+
+    const fn json_number(bytes: &[u8], start: usize) -> usize {
+        let mut index = start;
+
+        if index < bytes.len() && bytes[index] == b'-' {
+            index += 1;
+        }
+
+        index = if index < bytes.len() && bytes[index] == b'0' {
+            index + 1
+        } else {
+            json_digits(bytes, index)
+        };
+
+        if index != JSON_INVALID && index < bytes.len() && bytes[index] == b'.' {
+            index = json_digits(bytes, index + 1);
+        }
+
+        if index != JSON_INVALID && index < bytes.len() && matches!(bytes[index], b'e' | b'E') {
+            index += 1;
+
+            if index < bytes.len() && matches!(bytes[index], b'+' | b'-') {
+                index += 1;
+            }
+
+            index = json_digits(bytes, index);
+        }
+
+        index
+    }
+
+    const fn json_string(bytes: &[u8], start: usize) -> usize {
+        let mut index = start + 1;
+
+        while index < bytes.len() {
+            match bytes[index] {
+                b'"' => return index + 1,
+                b'\\' if index + 1 < bytes.len() => match bytes[index + 1] {
+                    b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't' => index += 2,
+                    b'u' => {
+                        let mut digit = 0;
+
+                        while digit < 4 {
+                            let position = index + 2 + digit;
+
+                            if position >= bytes.len() || !bytes[position].is_ascii_hexdigit() {
+                                return JSON_INVALID;
+                            }
+
+                            digit += 1;
+                        }
+
+                        index += 6;
+                    },
+                    _ => return JSON_INVALID,
+                },
+                0x00..=0x1F | b'\\' => return JSON_INVALID,
+                _ => index += 1,
+            }
+        }
+
+        JSON_INVALID
+    }
+
+    const fn json_sequence(bytes: &[u8], start: usize, close: u8) -> usize {
+        let mut index = json_whitespace(bytes, start + 1);
+
+        if index < bytes.len() && bytes[index] == close {
+            return index + 1;
+        }
+
+        loop {
+            if close == b'}' {
+                if index >= bytes.len() || bytes[index] != b'"' {
+                    return JSON_INVALID;
+                }
+
+                index = json_string(bytes, index);
+
+                if index == JSON_INVALID {
+                    return JSON_INVALID;
+                }
+
+                index = json_whitespace(bytes, index);
+
+                if index >= bytes.len() || bytes[index] != b':' {
+                    return JSON_INVALID;
+                }
+
+                index = json_whitespace(bytes, index + 1);
+            }
+
+            index = json_value(bytes, index);
+
+            if index == JSON_INVALID {
+                return JSON_INVALID;
+            }
+
+            index = json_whitespace(bytes, index);
+
+            if index >= bytes.len() {
+                return JSON_INVALID;
+            }
+
+            if bytes[index] == close {
+                return index + 1;
+            }
+
+            if bytes[index] != b',' {
+                return JSON_INVALID;
+            }
+
+            index = json_whitespace(bytes, index + 1);
+        }
+    }
+
+    const fn json_value(bytes: &[u8], start: usize) -> usize {
+        if start >= bytes.len() {
+            return JSON_INVALID;
+        }
+
+        match bytes[start] {
+            b'{' => json_sequence(bytes, start, b'}'),
+            b'[' => json_sequence(bytes, start, b']'),
+            b'"' => json_string(bytes, start),
+            b't' => json_literal(bytes, start, b"true"),
+            b'f' => json_literal(bytes, start, b"false"),
+            b'n' => json_literal(bytes, start, b"null"),
+            b'-' | b'0'..=b'9' => json_number(bytes, start),
+            _ => JSON_INVALID,
+        }
+    }
+
+    pub const fn squish(lines: &[&[u8]], output: &mut [u8]) -> usize {
+        let mut buffer = Buffer::new(output);
+        let mut empty = true;
+        let mut space = false;
+        let mut quote = Option::None;
+        let mut line = 0;
+
+        while line < lines.len() {
+            let bytes = lines[line];
+            let mut index = 0;
+
+            if quote.is_some() {
+                buffer.push(b' ');
+            } else {
+                space = true;
+            }
+
+            while index < bytes.len() {
+                let byte = bytes[index];
+
+                if quote.is_none() && matches!(byte, b' ' | b'\t' | b'\n' | b'\r') {
+                    space = true;
+                } else if quote.is_none()
+                    && byte == b'-'
+                    && index + 1 < bytes.len()
+                    && bytes[index + 1] == b'-'
+                {
+                    // The comment ends with the line.
+                    while index < bytes.len() && bytes[index] != b'\n' {
+                        index += 1;
+                    }
+
+                    space = true;
+                    continue;
+                } else {
+                    if space && !empty {
+                        buffer.push(b' ');
+                    }
+
+                    match quote {
+                        Option::Some(open) if open == byte => quote = Option::None,
+                        Option::None if matches!(byte, b'\'' | b'"') => {
+                            quote = Option::Some(byte);
+                        },
+                        _ => {},
+                    }
+
+                    buffer.push(byte);
+                    empty = false;
+                    space = false;
+                }
+
+                index += 1;
+            }
+
+            line += 1;
+        }
+
+        buffer.finish()
+    }
+
+    #[cfg(feature = "ip")]
+    pub trait AddressText {
+        const TEXT: &'static str;
+    }
+
+    #[cfg(feature = "ip")]
+    pub struct AddressKind<const SOCKET: bool>;
+
+    #[cfg(feature = "ip")]
+    pub trait Address<T: AddressText> {
+        type Output;
+        const VALUE: Self::Output;
+    }
+
+    #[cfg(feature = "ip")]
+    impl<T: AddressText> Address<T> for AddressKind<false> {
+        type Output = IpAddr;
+
+        const VALUE: IpAddr = match ip_address(T::TEXT.as_bytes()) {
+            Option::Some(address) => address,
+            Option::None => panic!("invalid IP address"),
+        };
+    }
+
+    #[cfg(feature = "ip")]
+    impl<T: AddressText> Address<T> for AddressKind<true> {
+        type Output = SocketAddr;
+
+        const VALUE: SocketAddr = match socket_address(T::TEXT.as_bytes()) {
+            Option::Some(address) => address,
+            Option::None => panic!("invalid IP address or socket address"),
+        };
+    }
+
+    #[cfg(feature = "ip")]
+    pub const fn is_socket_address(text: &str) -> bool {
+        let bytes = text.as_bytes();
+
+        if !bytes.is_empty() && bytes[0] == b'[' {
+            return true;
+        }
+
+        // An IPv6 address has at least two colons.
+        let mut colons = 0;
+        let mut index = 0;
+
+        while index < bytes.len() {
+            if bytes[index] == b':' {
+                colons += 1;
+            }
+
+            index += 1;
+        }
+
+        colons == 1
+    }
+
+    #[cfg(feature = "ip")]
+    const fn ip_address(bytes: &[u8]) -> Option<IpAddr> {
+        if let Option::Some([a, b, c, d]) = ipv4_octets(bytes, 0, bytes.len()) {
+            return Option::Some(IpAddr::V4(Ipv4Addr::new(a, b, c, d)));
+        }
+
+        match ipv6_segments(bytes, 0, bytes.len()) {
+            Option::Some([a, b, c, d, e, f, g, h]) => {
+                Option::Some(IpAddr::V6(Ipv6Addr::new(a, b, c, d, e, f, g, h)))
+            },
+            Option::None => Option::None,
+        }
+    }
+
+    #[cfg(feature = "ip")]
+    const fn socket_address(bytes: &[u8]) -> Option<SocketAddr> {
+        if !bytes.is_empty() && bytes[0] == b'[' {
+            let mut close = 1;
+
+            while close < bytes.len() && bytes[close] != b']' {
+                close += 1;
+            }
+
+            if close + 1 >= bytes.len() || bytes[close + 1] != b':' {
+                return Option::None;
+            }
+
+            let mut percent = 1;
+
+            while percent < close && bytes[percent] != b'%' {
+                percent += 1;
+            }
+
+            let scope = if percent < close {
+                match address_number(bytes, percent + 1, close, u32::MAX as u64) {
+                    Option::Some(scope) => scope as u32,
+                    Option::None => return Option::None,
+                }
+            } else {
+                0
+            };
+
+            let [a, b, c, d, e, f, g, h] = match ipv6_segments(bytes, 1, percent) {
+                Option::Some(segments) => segments,
+                Option::None => return Option::None,
+            };
+
+            return match address_number(bytes, close + 2, bytes.len(), u16::MAX as u64) {
+                Option::Some(port) => Option::Some(SocketAddr::V6(SocketAddrV6::new(
+                    Ipv6Addr::new(a, b, c, d, e, f, g, h),
+                    port as u16,
+                    0,
+                    scope,
+                ))),
+                Option::None => Option::None,
+            };
+        }
+
+        let mut colon = 0;
+
+        while colon < bytes.len() && bytes[colon] != b':' {
+            colon += 1;
+        }
+
+        let [a, b, c, d] = match ipv4_octets(bytes, 0, colon) {
+            Option::Some(octets) => octets,
+            Option::None => return Option::None,
+        };
+
+        match address_number(bytes, colon + 1, bytes.len(), u16::MAX as u64) {
+            Option::Some(port) => Option::Some(SocketAddr::V4(SocketAddrV4::new(
+                Ipv4Addr::new(a, b, c, d),
+                port as u16,
+            ))),
+            Option::None => Option::None,
+        }
+    }
+
+    #[cfg(feature = "ip")]
+    const fn ipv4_octets(bytes: &[u8], start: usize, end: usize) -> Option<[u8; 4]> {
+        let mut octets = [0; 4];
+        let mut count = 0;
+        let mut index = start;
+
+        loop {
+            let mut part_end = index;
+
+            while part_end < end && bytes[part_end] != b'.' {
+                part_end += 1;
+            }
+
+            // Leading zeros are rejected like `Ipv4Addr::from_str`.
+            if count == 4 || part_end - index > 3 || part_end - index > 1 && bytes[index] == b'0' {
+                return Option::None;
+            }
+
+            octets[count] = match address_number(bytes, index, part_end, u8::MAX as u64) {
+                Option::Some(octet) => octet as u8,
+                Option::None => return Option::None,
+            };
+
+            count += 1;
+
+            if part_end == end {
+                break;
+            }
+
+            index = part_end + 1;
+        }
+
+        if count == 4 {
+            Option::Some(octets)
+        } else {
+            Option::None
+        }
+    }
+
+    #[cfg(feature = "ip")]
+    const fn ipv6_segments(bytes: &[u8], start: usize, end: usize) -> Option<[u16; 8]> {
+        let mut segments = [0; 8];
+        let mut count = 0;
+        let mut compressed = Option::None;
+        let mut index = start;
+
+        if end - start >= 2 && bytes[start] == b':' && bytes[start + 1] == b':' {
+            compressed = Option::Some(0);
+            index += 2;
+        }
+
+        while index < end {
+            let mut group_end = index;
+            let mut dotted = false;
+
+            while group_end < end && bytes[group_end] != b':' {
+                dotted |= bytes[group_end] == b'.';
+                group_end += 1;
+            }
+
+            if dotted {
+                // An IPv4 address can only be the last 32 bits.
+                let [a, b, c, d] = match ipv4_octets(bytes, index, end) {
+                    Option::Some(octets) if group_end == end && count <= 6 => octets,
+                    _ => return Option::None,
+                };
+
+                segments[count] = u16::from_be_bytes([a, b]);
+                segments[count + 1] = u16::from_be_bytes([c, d]);
+                count += 2;
+                break;
+            }
+
+            if count == 8 || group_end - index == 0 || group_end - index > 4 {
+                return Option::None;
+            }
+
+            let mut value = 0;
+
+            while index < group_end {
+                value = match (bytes[index] as char).to_digit(16) {
+                    Option::Some(digit) => value * 16 + digit as u16,
+                    Option::None => return Option::None,
+                };
+
+                index += 1;
+            }
+
+            segments[count] = value;
+            count += 1;
+
+            if group_end == end {
+                break;
+            }
+
+            index = group_end + 1;
+
+            if index == end {
+                return Option::None;
+            }
+
+            if bytes[index] == b':' {
+                if compressed.is_some() {
+                    return Option::None;
+                }
+
+                compressed = Option::Some(count);
+                index += 1;
+            }
+        }
+
+        match compressed {
+            // `::` stands for at least one segment.
+            Option::Some(position) if count < 8 => {
+                let mut moved = 0;
+
+                while moved < count - position {
+                    segments[7 - moved] = segments[count - 1 - moved];
+                    segments[count - 1 - moved] = 0;
+                    moved += 1;
+                }
+
+                Option::Some(segments)
+            },
+            Option::None if count == 8 => Option::Some(segments),
+            _ => Option::None,
+        }
+    }
+
+    #[cfg(feature = "ip")]
+    const fn address_number(bytes: &[u8], start: usize, end: usize, max: u64) -> Option<u64> {
+        if start >= end {
+            return Option::None;
+        }
+
+        let mut value: u64 = 0;
+        let mut index = start;
+
+        while index < end {
+            if !bytes[index].is_ascii_digit() {
+                return Option::None;
+            }
+
+            value = value * 10 + (bytes[index] - b'0') as u64;
+
+            if value > max {
+                return Option::None;
+            }
+
+            index += 1;
+        }
+
+        Option::Some(value)
+    }
+}